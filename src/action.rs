@@ -12,8 +12,9 @@ use crate::error::{
 use crate::eventqueue::ActorEvent;
 use crate::game_state::game::{Game, GameUpdate};
 use crate::game_state::world::FloorTile;
-use crate::inventory::Item;
-use crate::static_data::RecipeDefiniton;
+use crate::inventory::{BasicInventory, Item};
+use crate::recording::db::RecordingId;
+use crate::static_data::{Data, ObjectDescriptor, RecipeDefiniton};
 use std::collections::HashMap;
 
 pub type ItemUseFn = fn(usize, Coordinate, AbsoluteDirection, &Game) -> Result<GameUpdate>;
@@ -31,8 +32,70 @@ pub enum SubAction {
     Drop(usize),
     Use(usize),
     ActivateBuilding,
+    // Move an item from the actor's inventory into the building in front.
+    Insert(usize),
+    // Take an item out of the building in front.
+    Extract,
     Craft(&'static RecipeDefiniton),
     Wait,
+    // Control steps. These only appear in recordings, and are resolved without taking a turn.
+    // Targets are indices into the recording's command list.
+    Jump(usize),
+    JumpIf(Condition, usize),
+    // Placed at the end of a block: run the steps from the given index up to here the given number of times.
+    Repeat(usize, u32),
+    // Run another recording, then continue with the next step.
+    Call(RecordingId),
+}
+
+// A test of the world around an actor, for conditional recording steps.
+// "Ahead" is the tile in the step's direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Condition {
+    FloorItemIs(&'static ObjectDescriptor),
+    InventoryHas(&'static ObjectDescriptor),
+    TileAheadOccupied,
+    ParadoxAbove(f64),
+}
+
+pub fn evaluate_condition(
+    condition: Condition,
+    actor: TrackableId,
+    direction: Direction,
+    game: &Game,
+) -> Result<bool> {
+    let location = game.world.actors.get_location(&actor)?;
+    let actor = game
+        .world
+        .actors
+        .get(location)?
+        .as_ref()
+        .ok_or(Error("No actor at expected coordinates"))?;
+
+    match condition {
+        Condition::FloorItemIs(definition) => {
+            let floor = game.world.items.get(location)?;
+            Ok(floor[0].is_some_and(|item| item.definition == definition))
+        }
+        Condition::InventoryHas(definition) => Ok(actor
+            .inventory
+            .get_items()
+            .iter()
+            .any(|i| i.is_some_and(|item| item.definition == definition))),
+        Condition::TileAheadOccupied => {
+            let orientation = actor.facing.rotate(&direction);
+            let ahead = Coordinate::as_offset(Coordinate { x: 0, y: 1 }, *location, orientation);
+            // The edge of the world counts as occupied.
+            match game.world.actors.get(&ahead) {
+                Err(OutOfBounds) => Ok(true),
+                Err(err) => Err(err),
+                Ok(cell) => Ok(cell.is_some()
+                    || game.world.items.get(&ahead)?[0].is_some()
+                    || *game.world.floor.get(&ahead)? == FloorTile::Water),
+            }
+        }
+        Condition::ParadoxAbove(threshold) => Ok(game.world.paradox.get(location)?.0 > threshold),
+    }
 }
 
 pub fn execute_action(actor: TrackableId, action: Action, game: &Game) -> Result<GameUpdate> {
@@ -51,10 +114,47 @@ pub fn execute_action(actor: TrackableId, action: Action, game: &Game) -> Result
         SubAction::Drop(idx) => execute_drop(idx, *location, orientation, game),
         SubAction::Craft(recipe) => execute_craft(recipe, *location, orientation, game), // _ => world,
         SubAction::ActivateBuilding => execute_use_building(*location, game),
+        SubAction::Insert(idx) => execute_insert(idx, *location, orientation, game),
+        SubAction::Extract => execute_extract(*location, orientation, game),
         SubAction::Wait => Ok(GameUpdate::new()),
+        SubAction::Jump(_) | SubAction::JumpIf(_, _) | SubAction::Repeat(_, _) | SubAction::Call(_) => {
+            Err(ActionFail("control steps can't be performed"))
+        }
     }
 }
 
+// Number of turns an action takes. The action only takes effect on its final turn.
+pub fn action_duration(actor: TrackableId, action: Action, game: &Game) -> Result<u32> {
+    let location = game.world.actors.get_location(&actor)?;
+    let actor = game
+        .world
+        .actors
+        .get(location)?
+        .as_ref()
+        .ok_or(Error("No actor at expected coordinates"))?;
+    let orientation = actor.facing.rotate(&action.direction);
+
+    let turns = match action.action {
+        SubAction::Move => {
+            let dst_coord = Coordinate::as_offset(Coordinate { x: 0, y: 1 }, *location, orientation);
+            // Out of bounds moves fail when executed, so their duration doesn't matter.
+            game.world.floor.get(&dst_coord).map_or(1, |tile| tile.move_cost())
+        }
+        SubAction::Craft(recipe) => recipe.turns.unwrap_or(1),
+        SubAction::Use(idx) => match actor.inventory.get_items().get(idx) {
+            Some(Some(item)) if item.definition.on_use.as_deref() == Some("action_construct") => game
+                .data
+                .buildings
+                .get(&item.definition.name)
+                .and_then(|building| building.build_turns)
+                .unwrap_or(1),
+            _ => 1,
+        },
+        _ => 1,
+    };
+    Ok(turns.max(1))
+}
+
 fn execute_move(
     location: Coordinate,
     orientation: AbsoluteDirection,
@@ -169,6 +269,70 @@ fn execute_drop(
     }
 }
 
+// Actor and building for actions on the building in front of the actor.
+fn get_facing_building(
+    update: &mut GameUpdate,
+    location: Coordinate,
+    orientation: AbsoluteDirection,
+    game: &Game,
+) -> Result<(Actor, Coordinate, Building)> {
+    let target = Coordinate::as_offset(Coordinate { x: 0, y: 1 }, location, orientation);
+
+    let mut actor = update
+        .world
+        .actor_updates
+        .get(&game.world.actors, &location)?
+        .ok_or(Error("actor Missing"))?;
+    actor.facing = orientation;
+    let building = match update.world.building_updates.get(&game.world.buildings, &target) {
+        Err(OutOfBounds) | Ok(None) => Err(ActionFail("no building")),
+        Err(err) => Err(err),
+        Ok(Some(building)) => Ok(building.clone()),
+    }?;
+    Ok((actor, target, building))
+}
+
+fn execute_insert(
+    idx: usize,
+    location: Coordinate,
+    orientation: AbsoluteDirection,
+    game: &Game,
+) -> Result<GameUpdate> {
+    let mut update: GameUpdate = GameUpdate::new();
+
+    let (mut actor, target, mut building) =
+        get_facing_building(&mut update, location, orientation, game)?;
+    let item = actor
+        .inventory
+        .remove_idx(idx)
+        .ok_or(ActionFail("No item in slot"))?;
+    building.input.insert(item)?;
+
+    update.world.actor_updates.set(&location, &Some(actor))?;
+    update.world.building_updates.set(&target, &Some(building))?;
+    Ok(update)
+}
+
+fn execute_extract(
+    location: Coordinate,
+    orientation: AbsoluteDirection,
+    game: &Game,
+) -> Result<GameUpdate> {
+    let mut update: GameUpdate = GameUpdate::new();
+
+    let (mut actor, target, mut building) =
+        get_facing_building(&mut update, location, orientation, game)?;
+    let item = building
+        .extractable()
+        .take_first()
+        .ok_or(ActionFail("nothing to extract"))?;
+    actor.inventory.insert(item)?;
+
+    update.world.actor_updates.set(&location, &Some(actor))?;
+    update.world.building_updates.set(&target, &Some(building))?;
+    Ok(update)
+}
+
 fn execute_use_item(
     idx: usize,
     location: Coordinate,
@@ -218,7 +382,7 @@ fn execute_use_cloner(
             let recorder = source_actor.inventory.get_items()[idx].ok_or(ActionFail("no item"))?;
             let recordingid = recorder
                 .recording
-                .ok_or(Error("called use cloner on a non-recorder item"))?;
+                .ok_or(ActionFail("cloner has no recording"))?;
 
             let actor_id = update.world.actor_updates.get_next_id(&game.world.actors);
 
@@ -235,11 +399,13 @@ fn execute_use_cloner(
                 .world
                 .actor_updates
                 .set(&dst_coord, &Some(new_actor))?;
-            update.eventqueue.this_turn.push_front(ActorEvent {
-                actor: actor_id,
-                recording: recordingid,
-                recording_idx: 0,
-            });
+            // The clone acts straight away unless its cloner delays it.
+            let mut evt = ActorEvent::new(actor_id, recordingid);
+            evt.interval = recorder.deployment.interval;
+            match recorder.deployment.delay {
+                0 => update.eventqueue.this_turn.push_front(evt),
+                delay => update.eventqueue.schedule(game.event_queue.turn + i64::from(delay), evt),
+            }
             Ok(update)
         }
     }
@@ -281,15 +447,33 @@ fn execute_construct(
             update.world.actor_updates.set(&location, &Some(actor))?;
             update.world.building_updates.set(
                 &location,
-                &Some(Building {
-                    definition: building_def,
-                }),
+                &Some(Building::new(building_def, orientation)),
             )?;
             Ok(update)
         }
     }
 }
 
+// Removes a recipe's ingredients from an inventory.
+pub fn pay_ingredients(
+    recipe: &RecipeDefiniton,
+    inventory: &mut BasicInventory,
+    data: &'static Data,
+) -> Result<()> {
+    for idx in 0..recipe.ingredients.len() {
+        let ingredient_definiton = data
+            .items
+            .get(&recipe.ingredients[idx])
+            .ok_or(Error("ingredient undefined"))?;
+
+        let ingedient: Item = Item::new(ingredient_definiton, 1);
+        for _ in 0..recipe.ingredient_counts[idx] as u16 {
+            inventory.remove(ingedient)?;
+        }
+    }
+    Ok(())
+}
+
 fn execute_craft(
     recipe: &RecipeDefiniton,
     location: Coordinate,
@@ -310,25 +494,22 @@ fn execute_craft(
         .ok_or(Error("product undefined"))?;
     let product: Item = Item::new(product_definiton, recipe.product_count as u16);
 
+    if let Some(station) = &recipe.station {
+        let front = Coordinate::as_offset(Coordinate { x: 0, y: 1 }, location, orientation);
+        let at_station = [location, front].iter().any(|c| {
+            matches!(game.world.buildings.get(c), Ok(Some(b)) if b.definition.name == *station)
+        });
+        if !at_station {
+            return Err(ActionFail("not at the required crafting station"));
+        }
+    }
+
     if let Some(actor) = actor_cell {
         let mut actor = actor.clone();
         actor.facing = orientation;
 
         let inventory = &mut actor.inventory;
-
-        for idx in 0..recipe.ingredients.len() {
-            let ingredient_definiton = game
-                .data
-                .items
-                .get(&recipe.ingredients[idx])
-                .ok_or(Error("ingredient undefined"))?;
-
-            let ingedient: Item = Item::new(ingredient_definiton, 1);
-            for _ in 0..recipe.ingredient_counts[idx] as u16 {
-                inventory.remove(ingedient)?;
-            }
-        }
-
+        pay_ingredients(recipe, inventory, game.data)?;
         inventory.insert(product)?;
 
         update.world.actor_updates.set(&location, &Some(actor))?;
@@ -417,8 +598,8 @@ mod tests {
 
         let sample_recording_id = game.recordings.recordings.register_recording(Recording {
             command_list: actions,
-            inventory: Default::default(),
             should_loop: true,
+            ..Recording::blank()
         });
         let cloner_def = data.items.get(&"basic_cloner".to_string()).unwrap();
         let new_cloner = Item::new_cloner(cloner_def, sample_recording_id);
@@ -455,4 +636,66 @@ mod tests {
 
         assert_eq!(crafted_item.definition.text.name, "Echo Crystal");
     }
+
+    #[test]
+    fn craft_at_station() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 2 }, data);
+
+        let location = Coordinate { x: 0, y: 0 };
+        assert!(game.spawn(&location).is_ok());
+        let item_def = data.items.get("echo_crystal").unwrap();
+        let update = devtools::grant_item(Item::new(item_def, 3), location, &game).unwrap();
+        update.apply(&mut game).unwrap();
+
+        let recipe = data.recipes.get("auto_miner").unwrap();
+        assert_eq!(
+            execute_craft(recipe, location, AbsoluteDirection::N, &game).unwrap_err(),
+            ActionFail("not at the required crafting station")
+        );
+
+        let assembler = data.buildings.get("assembler").unwrap();
+        game.world
+            .buildings
+            .mut_set(&Coordinate { x: 0, y: 1 }, &Some(Building::new(assembler, AbsoluteDirection::N)))
+            .unwrap();
+        let update = execute_craft(recipe, location, AbsoluteDirection::N, &game);
+        update.unwrap().apply(&mut game).unwrap();
+
+        let crafted_item = game.get_player_actor().unwrap().inventory.get_items()[0].unwrap();
+        assert_eq!(crafted_item.definition.name, "auto_miner");
+    }
+
+    #[test]
+    fn insert_extract() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 2 }, data);
+
+        let location = Coordinate { x: 0, y: 0 };
+        let chest = Coordinate { x: 0, y: 1 };
+        assert!(game.spawn(&location).is_ok());
+        let chest_def = data.buildings.get("chest").unwrap();
+        game.world
+            .buildings
+            .mut_set(&chest, &Some(Building::new(chest_def, AbsoluteDirection::N)))
+            .unwrap();
+        let item = Item::new(data.items.get("raw_crystal").unwrap(), 1);
+        let update = devtools::grant_item(item, location, &game).unwrap();
+        update.apply(&mut game).unwrap();
+
+        assert_eq!(
+            execute_insert(0, location, AbsoluteDirection::S, &game).unwrap_err(),
+            ActionFail("no building")
+        );
+        let update = execute_insert(0, location, AbsoluteDirection::N, &game);
+        update.unwrap().apply(&mut game).unwrap();
+        let stored = game.world.buildings.get(&chest).unwrap().as_ref().unwrap();
+        assert_eq!(stored.input.get_items()[0], Some(item));
+        assert!(game.get_player_actor().unwrap().inventory.get_items()[0].is_none());
+
+        let update = execute_extract(location, AbsoluteDirection::N, &game);
+        update.unwrap().apply(&mut game).unwrap();
+        assert_eq!(game.get_player_actor().unwrap().inventory.get_items()[0], Some(item));
+        assert!(execute_extract(location, AbsoluteDirection::N, &game).is_err());
+    }
 }
@@ -1,17 +1,40 @@
 use std::{cell::RefCell, rc::Rc};
+use crate::engine::tracking_worldlayer::TrackableId;
 use crate::error::Result;
 use crate::interface_egui::info;
-use crate::{game_state::game::Game, static_data::Data, worldgen};
+use crate::interface_egui::log::LogFilter;
+use crate::preview::{self, PreviewStep};
+use crate::recording::db::RecordingId;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::save;
+use crate::{game_state::game::Game, recording::Recording, static_data::Data, worldgen};
 use crate::interface_egui::{self, crafting::CraftingMenu, inventory, movement, recording::RecorderMenu, worldwindow::WorldWindowWidget};
 
 
 pub type GameFn = dyn Fn(&mut Game) -> Result<()>;
 
+// Predicted path of a recording, drawn over the world.
+pub struct PreviewState {
+    pub recording: RecordingId,
+    // Turn the prediction was made on. It's made again once the game moves on.
+    pub turn: i64,
+    pub steps: Result<Vec<PreviewStep>>,
+}
+
 pub struct Application {
     pub data: &'static Data,
     pub game: Rc<RefCell<Game>>,
     pub error: Result<()>,
     pub command: Option<Box<GameFn>>,
+    // Copy of a recording open in the editor.
+    pub editor: Option<Recording>,
+    pub preview: Option<PreviewState>,
+    // Recording text being imported or exported, and why it last failed to import.
+    pub recording_text: String,
+    pub recording_text_error: Option<String>,
+    // Actor the view follows instead of the player, while it exists.
+    pub camera: Option<TrackableId>,
+    pub log_filter: LogFilter,
 }
 
 impl Application {
@@ -22,6 +45,12 @@ impl Application {
             game: worldgen::start_game(data),
             error: Ok(()),
             command: None,
+            editor: None,
+            preview: None,
+            recording_text: String::new(),
+            recording_text_error: None,
+            camera: None,
+            log_filter: LogFilter::default(),
         }
     }
 
@@ -31,6 +60,43 @@ impl Application {
         }
     }
 
+    pub fn show_preview(&mut self, recording: RecordingId) {
+        let game = self.game.borrow();
+        self.preview = Some(PreviewState {
+            recording,
+            turn: game.score.turn,
+            steps: preview::predict(&game, recording),
+        });
+    }
+
+    // Replaces the game with a saved one. Anything pointing into the old game is dropped with it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(&mut self, path: &str) {
+        if self.error.is_err() {
+            return;
+        }
+        match save::load_from_file(path, self.data) {
+            Ok(game) => {
+                *self.game.borrow_mut() = game;
+                self.command = None;
+                self.editor = None;
+                self.preview = None;
+                self.camera = None;
+            }
+            Err(err) => self.error = Err(err),
+        }
+    }
+
+    fn refresh_preview(&mut self) {
+        let game = self.game.borrow();
+        if let Some(state) = self.preview.as_mut() {
+            if state.turn != game.score.turn {
+                state.turn = game.score.turn;
+                state.steps = preview::predict(&game, state.recording);
+            }
+        }
+    }
+
     pub fn execute(&mut self) {
         if let Some(cmd) = self.command.as_ref() {
             let mut game = self.game.borrow_mut();
@@ -42,13 +108,20 @@ impl Application {
 
 impl eframe::App for Application {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.refresh_preview();
         egui::CentralPanel::default().show(ctx, |ui| {
             
             let painter = ui.painter();
             let area = painter.clip_rect();
             {
                 let game = self.game.borrow();
-                let window = WorldWindowWidget::new(&game);
+                let mut window = WorldWindowWidget::new(&game);
+                if let Some(Ok(steps)) = self.preview.as_ref().map(|p| &p.steps) {
+                    window.preview = steps;
+                }
+                if let Some(location) = self.camera.and_then(|id| game.world.actors.get_location(&id).ok()) {
+                    window.center = *location;
+                }
                 let shapes = window.paint(ctx ,area);
                 painter.extend(shapes);
             }
@@ -64,6 +137,18 @@ impl eframe::App for Application {
 
             RecorderMenu::show(self, ctx);
 
+            interface_egui::library::show(self, ctx);
+
+            interface_egui::editor::show(self, ctx);
+
+            interface_egui::roster::show(self, ctx);
+
+            interface_egui::log::show(self, ctx);
+
+            // No filesystem on web.
+            #[cfg(not(target_arch = "wasm32"))]
+            interface_egui::save::show(self, ctx);
+
             interface_egui::error::show(self, ctx);
         });
 
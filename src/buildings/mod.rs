@@ -1,19 +1,51 @@
 use std::collections::HashMap;
 
+use crate::direction::AbsoluteDirection;
 use crate::paradox::Paradox;
 use crate::engine::update::{Delta, UpdatableContainer, UpdatableContainerDelta};
 use crate::game_state::game::Game;
-use crate::inventory::Item;
+use crate::inventory::{Item, SlotInventory};
 use crate::static_data::ObjectDescriptor;
 use crate::{datatypes::Coordinate, game_state::game::GameUpdate};
 use crate::error::{Result, Status::{ActionFail,Error}};
 
-pub type BuildingUseFn = fn(Coordinate, &Game) -> Result<GameUpdate>;
+// Building functions receive the building's location and facing.
+pub type BuildingUseFn = fn(Coordinate, AbsoluteDirection, &Game) -> Result<GameUpdate>;
+// Called once per turn for every building with an on_tick hook. ActionFail means the building is idle.
+pub type BuildingTickFn = fn(Coordinate, AbsoluteDirection, &Game) -> Result<GameUpdate>;
 
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Building {
     pub definition: &'static ObjectDescriptor,
+    // Set from the builder's facing when constructed. Only matters for directional buildings.
+    pub facing: AbsoluteDirection,
+    pub input: SlotInventory,
+    pub output: SlotInventory,
+}
+
+impl Building {
+    pub fn new(definition: &'static ObjectDescriptor, facing: AbsoluteDirection) -> Building {
+        Building {
+            definition,
+            facing,
+            input: SlotInventory::new(definition.input_slots.unwrap_or(0)),
+            output: SlotInventory::new(definition.output_slots.unwrap_or(0)),
+        }
+    }
+
+    // The inventory that actors extract from.
+    pub fn extractable(&mut self) -> &mut SlotInventory {
+        if self.output.get_items().is_empty() {
+            &mut self.input
+        } else {
+            &mut self.output
+        }
+    }
+}
+
+fn facing_offset(facing: AbsoluteDirection) -> Coordinate {
+    Coordinate { x: 0, y: 1 } * facing
 }
 
 
@@ -29,11 +61,12 @@ pub fn execute_use_building(
         .on_interact_fn
         .as_ref()
         .ok_or(ActionFail("Not a usable item"))?;
-    function(location, game)
+    function(location, building.facing, game)
 }
 
 fn use_ore_deposit(
     location: Coordinate,
+    _facing: AbsoluteDirection,
     game: &Game,
 ) -> Result<GameUpdate> {
     let mut update = GameUpdate::new();
@@ -55,17 +88,33 @@ fn use_ore_deposit(
 
 fn use_matter_digitizer(
     location: Coordinate,
+    _facing: AbsoluteDirection,
     game: &Game,
 ) -> Result<GameUpdate> {
     let mut update = GameUpdate::new();
 
     let floor = update.world.item_updates.get(&game.world.items, &location)?;
-    let item = floor[0].ok_or(ActionFail("no item to digitize"))?;
+    let item = match floor[0] {
+        Some(item) => {
+            update.world.item_updates.set(&location, &[None])?;
+            item
+        }
+        None => {
+            let mut building = update
+                .world
+                .building_updates
+                .get(&game.world.buildings, &location)?
+                .clone()
+                .ok_or(Error("No building"))?;
+            let item = building.input.take_first().ok_or(ActionFail("no item to digitize"))?;
+            update.world.building_updates.set(&location, &Some(building))?;
+            item
+        }
+    };
     let value = item.definition.score_value.unwrap_or(0);
 
             
     update.score.score += value;    
-    update.world.item_updates.set(&location, &[None])?;
 
 
     let paradox = update.world.paradox_updates.get(&game.world.paradox, &location)?;
@@ -78,6 +127,130 @@ fn use_matter_digitizer(
         
 }
 
+fn tick_auto_miner(
+    location: Coordinate,
+    _facing: AbsoluteDirection,
+    game: &Game,
+) -> Result<GameUpdate> {
+    let mut update = GameUpdate::new();
+
+    let deposit = [AbsoluteDirection::N, AbsoluteDirection::E, AbsoluteDirection::S, AbsoluteDirection::W]
+        .iter()
+        .map(|dir| location + facing_offset(*dir))
+        .filter(|neighbour| game.world.buildings.in_bounds(neighbour))
+        .any(|neighbour| {
+            matches!(game.world.buildings.get(&neighbour), Ok(Some(b)) if b.definition.name == "crystal_deposit")
+        });
+    if !deposit {
+        return Err(ActionFail("no adjacent deposit"));
+    }
+
+    let floor = update.world.item_updates.get(&game.world.items, &location)?;
+    if floor[0].is_some() {
+        return Err(ActionFail("output occupied"));
+    }
+    let oredef = game.data.items.get("raw_crystal").ok_or(Error("item definition not found"))?;
+    update.world.item_updates.set(&location, &[Some(Item::new(oredef, 1))])?;
+    Ok(update)
+}
+
+fn tick_smelter(
+    location: Coordinate,
+    _facing: AbsoluteDirection,
+    game: &Game,
+) -> Result<GameUpdate> {
+    let mut update = GameUpdate::new();
+    let productdef = game.data.items.get("echo_crystal").ok_or(Error("item definition not found"))?;
+
+    // Ore on the floor is refined in place, ore inserted into the smelter goes to its output.
+    let floor = update.world.item_updates.get(&game.world.items, &location)?;
+    if let Some(item) = floor[0] {
+        if item.definition.name == "raw_crystal" {
+            update.world.item_updates.set(&location, &[Some(Item::new(productdef, 1))])?;
+            return Ok(update);
+        }
+    }
+
+    let mut building = update
+        .world
+        .building_updates
+        .get(&game.world.buildings, &location)?
+        .clone()
+        .ok_or(Error("No building"))?;
+    let idx = building
+        .input
+        .get_items()
+        .iter()
+        .position(|i| i.is_some_and(|i| i.definition.name == "raw_crystal"))
+        .ok_or(ActionFail("nothing to smelt"))?;
+    building.output.insert(Item::new(productdef, 1))?;
+    building.input.remove_idx(idx);
+    update.world.building_updates.set(&location, &Some(building))?;
+    Ok(update)
+}
+
+fn tick_paradox_stabilizer(
+    location: Coordinate,
+    _facing: AbsoluteDirection,
+    game: &Game,
+) -> Result<GameUpdate> {
+    let mut update = GameUpdate::new();
+
+    let paradox = update.world.paradox_updates.get(&game.world.paradox, &location)?;
+    if paradox.0 <= 0.0 {
+        return Err(ActionFail("no paradox to stabilize"));
+    }
+    let new_paradox = (paradox.0 - 16.0).max(0.0);
+    update.world.paradox_updates.set(&location, &Paradox(new_paradox))?;
+    Ok(update)
+}
+
+fn is_loaded_conveyor(location: Coordinate, game: &Game) -> bool {
+    let Ok(Some(building)) = game.world.buildings.get(&location) else {
+        return false;
+    };
+    building.definition.on_tick.as_deref() == Some("tick_conveyor")
+        && matches!(game.world.items.get(&location), Ok([Some(_)]))
+}
+
+fn tick_conveyor(
+    location: Coordinate,
+    facing: AbsoluteDirection,
+    game: &Game,
+) -> Result<GameUpdate> {
+    let mut update = GameUpdate::new();
+
+    let target = location + facing_offset(facing);
+    if !game.world.items.in_bounds(&target) {
+        return Err(ActionFail("output blocked"));
+    }
+
+    // Buildings tick against the same state, so two conveyors feeding one tile would both see it empty.
+    // The conveyor that comes first in tick order (by x, then y) goes first; the rest wait.
+    let competing = [AbsoluteDirection::N, AbsoluteDirection::E, AbsoluteDirection::S, AbsoluteDirection::W]
+        .iter()
+        .map(|dir| target + facing_offset(*dir))
+        .filter(|source| *source != location && (source.x, source.y) < (location.x, location.y))
+        .filter(|source| is_loaded_conveyor(*source, game))
+        .any(|source| {
+            matches!(game.world.buildings.get(&source), Ok(Some(b)) if source + facing_offset(b.facing) == target)
+        });
+    if competing {
+        return Err(ActionFail("output blocked"));
+    }
+
+    let floor = update.world.item_updates.get(&game.world.items, &location)?;
+    let item = floor[0].ok_or(ActionFail("nothing to move"))?;
+    let output = update.world.item_updates.get(&game.world.items, &target)?;
+    if output[0].is_some() {
+        return Err(ActionFail("output blocked"));
+    }
+
+    update.world.item_updates.set(&location, &[None])?;
+    update.world.item_updates.set(&target, &[Some(item)])?;
+    Ok(update)
+}
+
 pub fn get_building_fn_table() -> HashMap<String, BuildingUseFn> {
     let mut map: HashMap<String, BuildingUseFn> = HashMap::new();
 
@@ -92,3 +265,26 @@ pub fn get_building_fn_table() -> HashMap<String, BuildingUseFn> {
 
     map
 }
+
+pub fn get_building_tick_fn_table() -> HashMap<String, BuildingTickFn> {
+    let mut map: HashMap<String, BuildingTickFn> = HashMap::new();
+
+    map.insert(
+        "tick_mine".to_string(),
+        tick_auto_miner,
+    );
+    map.insert(
+        "tick_smelt".to_string(),
+        tick_smelter,
+    );
+    map.insert(
+        "tick_stabilize".to_string(),
+        tick_paradox_stabilizer,
+    );
+    map.insert(
+        "tick_conveyor".to_string(),
+        tick_conveyor,
+    );
+
+    map
+}
@@ -2,9 +2,10 @@
 //! Any structure that increases in complexity should be moved to its own file.
 
 use crate::direction::AbsoluteDirection;
+use serde_derive::{Deserialize, Serialize};
 use std::{cmp::{max, min}, ops};
 
-#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Coordinate {
     pub x: i32,
     pub y: i32,
@@ -33,8 +33,8 @@ pub fn make_sample_recording() -> Recording {
                 action: SubAction::Move,
             },
         ],
-        inventory: Default::default(),
-        should_loop: true
+        should_loop: true,
+        ..Recording::blank()
     }
 }
 
@@ -81,15 +81,18 @@ pub fn remove_item(
     }
 }
 
+// Removes an actor on top of a pending update, so it can despawn after acting in the same turn.
 pub fn despawn_actor(
     actorid: TrackableId,
-    game: &Game
-) ->  Result<GameUpdate> {
-    let mut update: GameUpdate = GameUpdate::new();
-    let location = game.world.actors.get_location(&actorid)?;
+    game: &Game,
+    update: &mut GameUpdate,
+) ->  Result<()> {
+    let location = *update.world.actor_updates.get_location(&game.world.actors, &actorid)?;
     let actor  = update.world.actor_updates.get(&game.world.actors, &location)?;
     if actor.is_none() {return Err(Error("actor missing"))}
     update.world.actor_updates.set(&location, &None)?;
     update.world.actor_updates.remove(actorid);
-    Ok(update)
+    // Its id may be reused, so nothing queued for it can be left behind.
+    update.eventqueue.cancel(actorid);
+    Ok(())
 }
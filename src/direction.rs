@@ -1,11 +1,13 @@
-#[derive(Debug, PartialEq, Copy, Clone)]
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum AbsoluteDirection {
     N,
     S,
     E,
     W,
 }
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub enum RelativeDirection {
     F,
@@ -14,7 +16,7 @@ pub enum RelativeDirection {
     R,
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum Direction {
     Absolute(AbsoluteDirection),
     Relative(RelativeDirection),
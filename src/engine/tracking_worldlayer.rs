@@ -1,11 +1,12 @@
 use std::collections::{HashMap, HashSet};
+use serde_derive::{Deserialize, Serialize};
 use crate::error::Result;
 use crate::{datatypes::Coordinate, error::Status};
 
-use super::update::{Delta, Updatable, UpdatableContainer, UpdatableContainerDelta};
+use super::update::{Delta, MergeableDelta, Updatable, UpdatableContainer, UpdatableContainerDelta};
 use super::worldlayer::{WorldLayer, WorldLayerDelta};
 
-#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TrackableId(pub usize);
 
 pub trait Trackable {
@@ -21,6 +22,7 @@ impl<T: Trackable> Trackable for Option<T> {
     }
 }
 
+#[derive(Clone)]
 struct Index {
     data: HashMap<TrackableId, Coordinate>,
     recycle: HashSet<TrackableId>,
@@ -89,7 +91,7 @@ impl Delta for IndexDelta {
     }
 
     fn apply(&self, target: &mut Self::Target) -> Result<()> {
-        // TODO: validate. inserts and deletes do not intersect.
+        self.validate(target)?;
 
         for (k, v) in self.mutates.iter() {
             target.data.insert(*k, *v);
@@ -99,6 +101,38 @@ impl Delta for IndexDelta {
         }
         Ok(())
     }
+
+    fn validate(&self, _target: &Self::Target) -> Result<()> {
+        if self.inserts.iter().any(|k| self.deletes.contains(k)) {
+            return Err(Status::UpdateConflict("id created and removed in the same update"));
+        }
+        Ok(())
+    }
+}
+
+impl MergeableDelta for IndexDelta {
+    fn check_conflicts(&self, other: &Self) -> Result<()> {
+        for (k, v) in &other.mutates {
+            if self.mutates.get(k).is_some_and(|existing| existing != v) {
+                return Err(Status::UpdateConflict("conflicting locations for the same id"));
+            }
+        }
+        // Both deltas allocated ids from the same source, so any overlap is a double allocation.
+        if other.inserts.iter().any(|k| self.inserts.contains(k)) {
+            return Err(Status::UpdateConflict("the same id was allocated twice"));
+        }
+        Ok(())
+    }
+
+    fn absorb(&mut self, other: Self) {
+        self.mutates.extend(other.mutates);
+        self.inserts.extend(other.inserts);
+        for k in other.deletes {
+            if !self.deletes.contains(&k) {
+                self.deletes.push(k);
+            }
+        }
+    }
 }
 
 impl UpdatableContainerDelta for IndexDelta {
@@ -116,6 +150,7 @@ impl UpdatableContainerDelta for IndexDelta {
     }
 }
 
+#[derive(Clone)]
 pub struct TrackableWorldLayer<DataType: Clone + Trackable> {
     layer: WorldLayer<DataType>,
     index: Index,
@@ -140,6 +175,19 @@ impl<T: Clone + Trackable> TrackableWorldLayer<T> {
     pub fn mut_get_next_id(&mut self) ->TrackableId {
         self.index.get_next_id()
     }
+
+    // Raw index contents, used when saving and undoing. Recycled ids are included so that id allocation survives a reload.
+    pub fn get_index_entries(&self) -> (Vec<(TrackableId, Coordinate)>, Vec<TrackableId>) {
+        let data = self.index.data.iter().map(|(k, v)| (*k, *v)).collect();
+        let recycle = self.index.recycle.iter().copied().collect();
+        (data, recycle)
+    }
+
+    // Replaces the index wholesale. Used when loading saved games and undoing turns.
+    pub fn mut_restore_index(&mut self, data: &[(TrackableId, Coordinate)], recycle: &[TrackableId]) {
+        self.index.data = data.iter().copied().collect();
+        self.index.recycle = recycle.iter().copied().collect();
+    }
 }
 
 impl<T: Clone + Trackable> Updatable for TrackableWorldLayer<T> {}
@@ -194,10 +242,28 @@ impl<T: Clone + Trackable> Delta for TrackableWorldLayerDelta<T> {
     }
 
     fn apply(&self, target: &mut Self::Target) -> Result<()> {
+        self.validate(target)?;
         self.layer.apply(&mut target.layer)?;
         self.index.apply(&mut target.index)?;
         Ok(())
     }
+
+    fn validate(&self, target: &Self::Target) -> Result<()> {
+        self.layer.validate(&target.layer)?;
+        self.index.validate(&target.index)
+    }
+}
+
+impl<T: Clone + Trackable + PartialEq> MergeableDelta for TrackableWorldLayerDelta<T> {
+    fn check_conflicts(&self, other: &Self) -> Result<()> {
+        self.layer.check_conflicts(&other.layer)?;
+        self.index.check_conflicts(&other.index)
+    }
+
+    fn absorb(&mut self, other: Self) {
+        self.layer.absorb(other.layer);
+        self.index.absorb(other.index);
+    }
 }
 
 impl<T: Clone + Trackable> UpdatableContainerDelta for TrackableWorldLayerDelta<T> {
@@ -8,6 +8,28 @@ pub trait Delta {
     type Target: Updatable;
     fn new() -> Self;
     fn apply(&self, target: &mut Self::Target) -> Result<()>;
+
+    // Check that the delta can be applied in full. Applying a delta should validate first,
+    // so that a failing update leaves its target unchanged.
+    fn validate(&self, _target: &Self::Target) -> Result<()> {
+        Ok(())
+    }
+}
+
+// A delta that can be combined with another delta computed against the same state.
+pub trait MergeableDelta: Delta + Sized {
+    // Fails if both deltas write different values to the same key.
+    fn check_conflicts(&self, other: &Self) -> Result<()>;
+
+    // Take all writes from other. Only call after check_conflicts.
+    fn absorb(&mut self, other: Self);
+
+    // Merge two deltas. On conflict, self is left unchanged.
+    fn merge(&mut self, other: Self) -> Result<()> {
+        self.check_conflicts(&other)?;
+        self.absorb(other);
+        Ok(())
+    }
 }
 
 pub trait UpdatableContainer {
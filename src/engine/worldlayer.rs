@@ -2,9 +2,9 @@ use std::vec;
 
 use std::collections::HashMap;
 
-use super::update::{Delta, Updatable, UpdatableContainer, UpdatableContainerDelta};
+use super::update::{Delta, MergeableDelta, Updatable, UpdatableContainer, UpdatableContainerDelta};
 use crate::datatypes::Coordinate;
-use crate::error::Result;
+use crate::error::{Result, Status};
 
 #[derive(Debug, Clone)]
 pub struct WorldLayer<DataType: Clone> {
@@ -69,11 +69,45 @@ impl<DataType: Clone> Delta for WorldLayerDelta<DataType> {
     }
 
     fn apply(&self, target: &mut WorldLayer<DataType>) -> Result<()> {
+        self.validate(target)?;
         for (k, v) in &self.writes {
             target.mut_set(k, v)?
         }
         Ok(())
     }
+
+    fn validate(&self, target: &WorldLayer<DataType>) -> Result<()> {
+        if self.writes.keys().all(|k| target.in_bounds(k)) {
+            Ok(())
+        } else {
+            Err(Status::OutOfBounds)
+        }
+    }
+}
+
+impl<DataType: Clone> WorldLayerDelta<DataType> {
+    // Stricter than check_conflicts: any cell written by both is a conflict, even if the writes agree.
+    pub fn check_disjoint(&self, other: &Self) -> Result<()> {
+        if other.writes.keys().any(|k| self.writes.contains_key(k)) {
+            return Err(Status::UpdateConflict("two writes to the same cell"));
+        }
+        Ok(())
+    }
+}
+
+impl<DataType: Clone + PartialEq> MergeableDelta for WorldLayerDelta<DataType> {
+    fn check_conflicts(&self, other: &Self) -> Result<()> {
+        for (k, v) in &other.writes {
+            if self.writes.get(k).is_some_and(|existing| existing != v) {
+                return Err(Status::UpdateConflict("conflicting writes to the same cell"));
+            }
+        }
+        Ok(())
+    }
+
+    fn absorb(&mut self, other: Self) {
+        self.writes.extend(other.writes);
+    }
 }
 
 impl<DataType: Clone> UpdatableContainerDelta for WorldLayerDelta<DataType> {
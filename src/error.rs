@@ -5,6 +5,9 @@ pub enum Status {
     OutOfBounds, 
     // A world state update failed to apply. This breaks any semblance of atomicity.
     StateUpdateError,
+    // Two updates wrote different values to the same cell or id, or an update contradicts itself.
+    // Nothing is applied.
+    UpdateConflict(&'static str),
     // An unexpected error. Panic.
     Error(&'static str),
 }
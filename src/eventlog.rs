@@ -0,0 +1,157 @@
+//! A record of what happened each turn, for the log window and headless runs.
+//!
+//! Only the most recent entries are kept. Turns that fail and are rolled back leave nothing behind.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::action::SubAction;
+use crate::datatypes::Coordinate;
+use crate::engine::tracking_worldlayer::TrackableId;
+use crate::static_data::{ObjectDescriptor, RecipeDefiniton};
+
+pub const MAX_LOG_ENTRIES: usize = 2000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LogEvent {
+    Moved,
+    Crafted(&'static RecipeDefiniton),
+    Dropped(&'static ObjectDescriptor),
+    // Any other action that succeeded.
+    Acted(SubAction),
+    Failed(SubAction, &'static str),
+    ParadoxDeath,
+    // Removed by a fallback policy or by the player.
+    Despawned,
+    // Despawned at the end of its recording.
+    Finished,
+    ScoreGained(i64),
+}
+
+// Groups of events that can be shown or hidden together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LogCategory {
+    Actions,
+    Failures,
+    Removals,
+    Score,
+}
+
+impl LogCategory {
+    pub const ALL: [LogCategory; 4] = [
+        LogCategory::Actions,
+        LogCategory::Failures,
+        LogCategory::Removals,
+        LogCategory::Score,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogCategory::Actions => "Actions",
+            LogCategory::Failures => "Failures",
+            LogCategory::Removals => "Deaths and despawns",
+            LogCategory::Score => "Score",
+        }
+    }
+}
+
+impl LogEvent {
+    pub fn category(&self) -> LogCategory {
+        match self {
+            LogEvent::Moved | LogEvent::Crafted(_) | LogEvent::Dropped(_) | LogEvent::Acted(_) => {
+                LogCategory::Actions
+            }
+            LogEvent::Failed(_, _) => LogCategory::Failures,
+            LogEvent::ParadoxDeath | LogEvent::Despawned | LogEvent::Finished => LogCategory::Removals,
+            LogEvent::ScoreGained(_) => LogCategory::Score,
+        }
+    }
+}
+
+// Short description of an action. Slots are numbered from 1, as in the game.
+fn action_text(action: &SubAction) -> String {
+    match action {
+        SubAction::Move => "move".to_string(),
+        SubAction::Take => "take".to_string(),
+        SubAction::Drop(slot) => format!("drop slot {}", slot + 1),
+        SubAction::Use(slot) => format!("use slot {}", slot + 1),
+        SubAction::ActivateBuilding => "activate building".to_string(),
+        SubAction::Insert(slot) => format!("insert slot {}", slot + 1),
+        SubAction::Extract => "extract".to_string(),
+        SubAction::Craft(recipe) => format!("craft {}", recipe.name),
+        SubAction::Wait => "wait".to_string(),
+        SubAction::Jump(_) | SubAction::JumpIf(_, _) => "jump".to_string(),
+        SubAction::Repeat(_, _) => "repeat".to_string(),
+        SubAction::Call(_) => "play recording".to_string(),
+    }
+}
+
+impl fmt::Display for LogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogEvent::Moved => write!(f, "moved"),
+            LogEvent::Crafted(recipe) => write!(f, "crafted {}", recipe.name),
+            LogEvent::Dropped(item) => write!(f, "dropped {}", item.text.name),
+            LogEvent::Acted(action) => write!(f, "{}", action_text(action)),
+            LogEvent::Failed(action, reason) => write!(f, "{} failed: {}", action_text(action), reason),
+            LogEvent::ParadoxDeath => write!(f, "died of paradox"),
+            LogEvent::Despawned => write!(f, "despawned"),
+            LogEvent::Finished => write!(f, "finished its recording"),
+            LogEvent::ScoreGained(score) => write!(f, "scored {}", score),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogEntry {
+    pub turn: i64,
+    // None for buildings.
+    pub actor: Option<TrackableId>,
+    pub location: Coordinate,
+    pub event: LogEvent,
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "turn {}, ", self.turn)?;
+        match self.actor {
+            Some(id) => write!(f, "actor {}", id.0)?,
+            None => write!(f, "building")?,
+        }
+        write!(f, " at ({}, {}): {}", self.location.x, self.location.y, self.event)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct EventLog {
+    entries: VecDeque<LogEntry>,
+}
+
+impl EventLog {
+    pub fn new() -> EventLog {
+        EventLog::default()
+    }
+
+    pub fn push(&mut self, entry: LogEntry) {
+        self.entries.push_back(entry);
+        if self.entries.len() > MAX_LOG_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+
+    // Entries from the given turn onwards.
+    pub fn since(&self, turn: i64) -> impl Iterator<Item = &LogEntry> {
+        let start = self.entries.partition_point(|e| e.turn < turn);
+        self.entries.range(start..)
+    }
+
+    // Forgets entries from the given turn onwards, when play goes back to before it.
+    pub fn discard_since(&mut self, turn: i64) {
+        let start = self.entries.partition_point(|e| e.turn < turn);
+        self.entries.truncate(start);
+    }
+}
@@ -1,28 +1,187 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::engine::tracking_worldlayer::TrackableId;
-use crate::engine::update::{Delta, Updatable};
+use crate::engine::update::{Delta, MergeableDelta, Updatable};
 use crate::recording::db::RecordingId;
+use crate::recording::db::RecordingDb;
+use crate::recording::{Recording, MAX_CALL_DEPTH};
 use  crate::error::{Status, Result};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActorEvent{
     pub actor: TrackableId,
     pub recording: RecordingId,
-    pub recording_idx: usize
+    pub recording_idx: usize,
+    // Turns already spent on the current step, for actions that take several turns.
+    pub progress: u32,
+    // Failed attempts at the current step, for the Retry fallback policy.
+    pub retries: u32,
+    // Completed plays of the whole recording.
+    pub plays: u32,
+    // Passes completed for each repeat block currently being run, keyed by the index of its Repeat step.
+    pub loop_counters: Vec<(usize, u32)>,
+    // Callers to return to when the current recording finishes.
+    pub call_stack: Vec<CallFrame>,
+    // Turns from finishing the recording to starting it again. Without one, the actor despawns when it finishes.
+    pub interval: Option<u32>,
+    pub last_result: Option<StepResult>,
 }
 
-#[derive(Debug)]
+// How an actor's last turn went.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StepResult {
+    Done,
+    // Partway through an action that takes several turns.
+    InProgress,
+    Failed(String),
+}
+
+// How a cloner deploys its clone.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Deployment {
+    // Turns the clone stands idle before its first step.
+    pub delay: u32,
+    pub interval: Option<u32>,
+}
 
+// Where to resume a recording that called another.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallFrame {
+    pub recording: RecordingId,
+    // Index of the Call step.
+    pub recording_idx: usize,
+    pub plays: u32,
+    pub loop_counters: Vec<(usize, u32)>,
+}
+
+impl ActorEvent {
+    pub fn new(actor: TrackableId, recording: RecordingId) -> ActorEvent {
+        ActorEvent {
+            actor,
+            recording,
+            recording_idx: 0,
+            progress: 0,
+            retries: 0,
+            plays: 0,
+            loop_counters: Vec::new(),
+            call_stack: Vec::new(),
+            interval: None,
+            last_result: None,
+        }
+    }
+
+    // Back to the start of the outermost recording, for another round of plays.
+    pub fn restart(&mut self) {
+        if let Some(frame) = self.call_stack.first() {
+            self.recording = frame.recording;
+        }
+        self.call_stack.clear();
+        self.recording_idx = 0;
+        self.progress = 0;
+        self.retries = 0;
+        self.plays = 0;
+        self.loop_counters.clear();
+    }
+
+    // Moves to a step of the current recording, starting another play if it runs off the end.
+    // Called recordings never loop forever, so that they always return.
+    // Returns false once the recording has finished.
+    fn goto_step(&mut self, recording: &Recording, next: usize) -> bool {
+        if next < recording.len() {
+            self.recording_idx = next;
+            return true;
+        }
+        let loops = recording.should_loop && self.call_stack.is_empty();
+        if !loops && self.plays + 1 >= recording.repeat_count {
+            return false;
+        }
+        self.plays = self.plays.saturating_add(1);
+        self.recording_idx = next % recording.len();
+        self.loop_counters.clear();
+        true
+    }
+
+    // Moves to a step, returning to the caller whenever a called recording finishes.
+    // Returns false once the outermost recording has finished.
+    pub fn advance(&mut self, recordings: &RecordingDb, next: usize) -> bool {
+        let mut next = next;
+        loop {
+            if self.goto_step(recordings.get(self.recording), next) {
+                return true;
+            }
+            let Some(frame) = self.call_stack.pop() else {
+                return false;
+            };
+            self.recording = frame.recording;
+            self.plays = frame.plays;
+            self.loop_counters = frame.loop_counters;
+            next = frame.recording_idx + 1;
+        }
+    }
+
+    // Starts running another recording, to return to the step after this one when it finishes.
+    // Fails on recursion or when the call stack is too deep.
+    pub fn call(&mut self, callee: RecordingId) -> Result<()> {
+        if self.recording == callee || self.call_stack.iter().any(|f| f.recording == callee) {
+            return Err(Status::ActionFail("recording calls itself"));
+        }
+        if self.call_stack.len() >= MAX_CALL_DEPTH {
+            return Err(Status::ActionFail("too many nested calls"));
+        }
+        self.call_stack.push(CallFrame {
+            recording: self.recording,
+            recording_idx: self.recording_idx,
+            plays: self.plays,
+            loop_counters: std::mem::take(&mut self.loop_counters),
+        });
+        self.recording = callee;
+        self.recording_idx = 0;
+        self.plays = 0;
+        Ok(())
+    }
+}
+
+// Actor events by the turn they happen on.
+// Events for the current and next turns are kept in their own queues, as nearly every event is one of those.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventQueue{
+    // The turn being played.
+    pub turn: i64,
     pub this_turn: VecDeque<ActorEvent>,
-    pub next_turn: VecDeque<ActorEvent>
+    pub next_turn: VecDeque<ActorEvent>,
+    // Events for later turns, keyed by the turn they happen on.
+    pub scheduled: BTreeMap<i64, VecDeque<ActorEvent>>,
+    // Events held back from paused actors, with the turn they were due on.
+    pub paused: Vec<(i64, ActorEvent)>
 }
 
+// Cancellations are applied before new events, so an actor can be cancelled and rescheduled in one update.
 #[derive(Debug)]
 pub struct EventQueueUpdate{
     pub this_turn: VecDeque<ActorEvent>,
-    pub next_turn: VecDeque<ActorEvent>
+    pub next_turn: VecDeque<ActorEvent>,
+    pub scheduled: BTreeMap<i64, VecDeque<ActorEvent>>,
+    pub cancelled: Vec<TrackableId>
+}
+
+impl EventQueueUpdate {
+    pub fn schedule(&mut self, turn: i64, evt: ActorEvent) {
+        self.scheduled.entry(turn).or_default().push_back(evt);
+    }
+
+    // Removes every queued event for the actor.
+    pub fn cancel(&mut self, actor: TrackableId) {
+        self.cancelled.push(actor);
+    }
+
+    fn events(&self) -> impl Iterator<Item = &ActorEvent> {
+        self.this_turn.iter().chain(&self.next_turn).chain(self.scheduled.values().flatten())
+    }
+
+    fn touches(&self, actor: TrackableId) -> bool {
+        self.cancelled.contains(&actor) || self.events().any(|e| e.actor == actor)
+    }
 }
 
 impl Updatable for EventQueue{}
@@ -32,23 +191,64 @@ impl Delta for EventQueueUpdate {
     fn new() -> Self {
         Self {
             this_turn: VecDeque::new(),
-            next_turn: VecDeque::new()
+            next_turn: VecDeque::new(),
+            scheduled: BTreeMap::new(),
+            cancelled: Vec::new()
         }
     }
 
     fn apply(&self, target: &mut EventQueue) -> Result<()> {
-        target.this_turn.extend(&self.this_turn);
-        target.next_turn.extend(&self.next_turn);
+        self.validate(target)?;
+        for actor in &self.cancelled {
+            target.cancel(*actor);
+        }
+        target.this_turn.extend(self.this_turn.iter().cloned());
+        target.next_turn.extend(self.next_turn.iter().cloned());
+        for (turn, events) in &self.scheduled {
+            target.scheduled.entry(*turn).or_default().extend(events.iter().cloned());
+        }
+        Ok(())
+    }
+
+    // Events scheduled for a turn that has already started would never run.
+    fn validate(&self, target: &EventQueue) -> Result<()> {
+        match self.scheduled.keys().next() {
+            Some(turn) if *turn <= target.turn => Err(Status::Error("event scheduled for a past turn")),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl MergeableDelta for EventQueueUpdate {
+    fn check_conflicts(&self, other: &Self) -> Result<()> {
+        let actors = other.events().map(|e| e.actor).chain(other.cancelled.iter().copied());
+        for actor in actors {
+            if self.touches(actor) {
+                return Err(Status::UpdateConflict("actor scheduled twice"));
+            }
+        }
         Ok(())
-    } 
+    }
+
+    fn absorb(&mut self, other: Self) {
+        self.this_turn.extend(other.this_turn);
+        self.next_turn.extend(other.next_turn);
+        for (turn, events) in other.scheduled {
+            self.scheduled.entry(turn).or_default().extend(events);
+        }
+        self.cancelled.extend(other.cancelled);
+    }
 }
 
 
 impl EventQueue {
     pub  fn new() -> Self {
         Self {
+            turn: 0,
             this_turn: VecDeque::new(),
-            next_turn: VecDeque::new()
+            next_turn: VecDeque::new(),
+            scheduled: BTreeMap::new(),
+            paused: Vec::new()
         }
     }
 
@@ -57,10 +257,143 @@ pub fn get_next_event(&mut self) -> Option<ActorEvent> {
     self.this_turn.pop_front()
 }
 
-pub fn advance_turn(&mut self) -> Result<()>{
+// Starts the given turn, bringing in the events scheduled for it after those carried over.
+pub fn advance_turn(&mut self, turn: i64) -> Result<()>{
     if !self.this_turn.is_empty() {return  Err(Status::StateUpdateError)};
     std::mem::swap(&mut self.this_turn, &mut self.next_turn);
+    let later = self.scheduled.split_off(&(turn + 1));
+    for (_, events) in std::mem::replace(&mut self.scheduled, later) {
+        self.this_turn.extend(events);
+    }
+    self.turn = turn;
     Ok(())
 }
 
-}
\ No newline at end of file
+// Queues an event the given number of turns from now. Zero is later this turn.
+pub fn schedule_in(&mut self, turns: u32, evt: ActorEvent) {
+    self.schedule_at(self.turn + i64::from(turns), evt);
+}
+
+// Events for past turns happen later this turn.
+fn schedule_at(&mut self, turn: i64, evt: ActorEvent) {
+    match turn - self.turn {
+        ..=0 => self.this_turn.push_back(evt),
+        1 => self.next_turn.push_back(evt),
+        _ => self.scheduled.entry(turn).or_default().push_back(evt),
+    }
+}
+
+// Removes every event for the actor, paused or not, returning how many there were.
+pub fn cancel(&mut self, actor: TrackableId) -> usize {
+    let mut removed = 0;
+    for queue in [&mut self.this_turn, &mut self.next_turn].into_iter().chain(self.scheduled.values_mut()) {
+        let before = queue.len();
+        queue.retain(|e| e.actor != actor);
+        removed += before - queue.len();
+    }
+    self.scheduled.retain(|_, queue| !queue.is_empty());
+    let before = self.paused.len();
+    self.paused.retain(|(_, e)| e.actor != actor);
+    removed + before - self.paused.len()
+}
+
+// Holds back the actor's events until it is resumed. Returns false if it had none.
+pub fn pause(&mut self, actor: TrackableId) -> bool {
+    let events: Vec<(i64, ActorEvent)> = self
+        .queued()
+        .filter(|(_, e)| e.actor == actor)
+        .map(|(turn, e)| (turn, e.clone()))
+        .collect();
+    if events.is_empty() {
+        return false;
+    }
+    self.cancel(actor);
+    self.paused.extend(events);
+    true
+}
+
+// Puts a paused actor's events back. Those that fell due while it was paused happen this turn.
+pub fn resume(&mut self, actor: TrackableId) -> bool {
+    let (resumed, paused): (Vec<_>, Vec<_>) =
+        std::mem::take(&mut self.paused).into_iter().partition(|(_, e)| e.actor == actor);
+    self.paused = paused;
+    if resumed.is_empty() {
+        return false;
+    }
+    for (turn, evt) in resumed {
+        self.schedule_at(turn, evt);
+    }
+    true
+}
+
+// Whether the actor has events held back by pause.
+pub fn is_paused(&self, actor: TrackableId) -> bool {
+    self.paused.iter().any(|(_, e)| e.actor == actor)
+}
+
+// Every queued event with the turn it happens on, soonest first.
+pub fn queued(&self) -> impl Iterator<Item = (i64, &ActorEvent)> {
+    let this_turn = self.this_turn.iter().map(|e| (self.turn, e));
+    let next_turn = self.next_turn.iter().map(|e| (self.turn + 1, e));
+    let later = self.scheduled.iter().flat_map(|(turn, events)| events.iter().map(|e| (*turn, e)));
+    this_turn.chain(next_turn).chain(later)
+}
+
+// The actor's next event, if it has one.
+pub fn next_for(&self, actor: TrackableId) -> Option<(i64, &ActorEvent)> {
+    self.queued().find(|(_, e)| e.actor == actor)
+}
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scheduler() {
+        let mut queue = EventQueue::new();
+        let recording = RecordingDb::new().register_recording(Recording::blank());
+        let evt = |actor| ActorEvent::new(TrackableId(actor), recording);
+        queue.schedule_in(3, evt(1));
+        queue.schedule_in(1, evt(2));
+        queue.schedule_in(3, evt(3));
+        let turns: Vec<(i64, usize)> = queue.queued().map(|(turn, e)| (turn, e.actor.0)).collect();
+        assert_eq!(turns, [(1, 2), (3, 1), (3, 3)]);
+
+        assert_eq!(queue.cancel(TrackableId(1)), 1);
+        assert_eq!(queue.next_for(TrackableId(3)).map(|(turn, _)| turn), Some(3));
+
+        // Cancelled and rescheduled in one update.
+        let mut update = EventQueueUpdate::new();
+        update.cancel(TrackableId(3));
+        update.schedule(2, evt(3));
+        update.apply(&mut queue).unwrap();
+        assert_eq!(queue.next_for(TrackableId(3)).map(|(turn, _)| turn), Some(2));
+
+        for turn in 1..=2 {
+            queue.advance_turn(turn).unwrap();
+            let actors: Vec<usize> = std::iter::from_fn(|| queue.get_next_event()).map(|e| e.actor.0).collect();
+            assert_eq!(actors, [turn as usize + 1]);
+        }
+        assert!(queue.queued().next().is_none());
+
+        // Paused events keep their place in line, or happen straight away if they fell due.
+        queue.schedule_in(1, evt(5));
+        queue.schedule_in(4, evt(6));
+        assert!(queue.pause(TrackableId(5)) && queue.pause(TrackableId(6)));
+        assert!(!queue.pause(TrackableId(5)));
+        assert!(queue.is_paused(TrackableId(5)));
+        assert_eq!(queue.queued().count(), 0);
+        queue.advance_turn(3).unwrap();
+        assert!(queue.resume(TrackableId(5)) && queue.resume(TrackableId(6)));
+        let turns: Vec<(i64, usize)> = queue.queued().map(|(turn, e)| (turn, e.actor.0)).collect();
+        assert_eq!(turns, [(3, 5), (6, 6)]);
+        assert_eq!(queue.cancel(TrackableId(6)), 1);
+        queue.get_next_event();
+
+        let mut update = EventQueueUpdate::new();
+        update.schedule(2, evt(4));
+        assert!(update.apply(&mut queue).is_err());
+    }
+}
@@ -3,28 +3,33 @@
 
 use crate::actor::Actor;
 use crate::engine::tracking_worldlayer::TrackableId;
-use crate::engine::update::{Delta, Updatable, UpdatableContainer};
+use crate::engine::update::{Delta, MergeableDelta, Updatable, UpdatableContainer};
+use crate::history::{GameSnapshot, History};
 use crate::recording::interface::RecordingModule;
-use crate::recording::Recording;
+use crate::action::SubAction;
+use crate::recording::{FallbackPolicy, Recording, MAX_CONTROL_STEPS, MAX_RETRIES};
 use crate::score::{Score, ScoreDelta};
 use crate::static_data::Data;
 use crate::{action, devtools, paradox};
 
 use crate::error::{
     Result,
-    Status::{ActionFail, Error},
+    Status::{ActionFail, Error, UpdateConflict},
 };
-use crate::eventqueue::{ActorEvent, EventQueue, EventQueueUpdate};
+use crate::eventlog::{EventLog, LogEntry, LogEvent};
+use crate::eventqueue::{ActorEvent, EventQueue, EventQueueUpdate, StepResult};
 use crate::{
     datatypes::Coordinate,
     game_state::world::{World, WorldUpdate},
 };
 
 // Move the WorldActors struct out to a dedicated module.
+#[derive(Clone)]
 pub struct WorldActors {
     pub player: Option<PlayerRef>,
 }
 
+#[derive(Clone)]
 pub struct PlayerRef {
     pub actor_id: TrackableId,
 }
@@ -50,6 +55,8 @@ pub struct Game {
     pub event_queue: EventQueue,
     pub data: &'static Data,
     pub score: Score,
+    pub history: History,
+    pub log: EventLog,
 }
 
 impl Updatable for Game {}
@@ -76,11 +83,39 @@ impl Delta for GameUpdate {
     }
 
     fn apply(&self, target: &mut Self::Target) -> Result<()> {
+        self.validate(target)?;
         self.world.apply(&mut target.world)?;
         self.eventqueue.apply(&mut target.event_queue)?;
         self.score.apply(&mut target.score)?;
         Ok(())
     }
+
+    fn validate(&self, target: &Self::Target) -> Result<()> {
+        self.world.validate(&target.world)?;
+        self.eventqueue.validate(&target.event_queue)?;
+        self.score.validate(&target.score)
+    }
+}
+
+impl MergeableDelta for GameUpdate {
+    fn check_conflicts(&self, other: &Self) -> Result<()> {
+        self.world.check_conflicts(&other.world)?;
+        self.eventqueue.check_conflicts(&other.eventqueue)?;
+        self.score.check_conflicts(&other.score)
+    }
+
+    fn absorb(&mut self, other: Self) {
+        self.world.absorb(other.world);
+        self.eventqueue.absorb(other.eventqueue);
+        self.score.absorb(other.score);
+    }
+}
+
+// What happens to an npc's place in its recording after its turn.
+enum StepOutcome {
+    Advance,
+    Repeat,
+    Despawn,
 }
 
 impl Game {
@@ -92,6 +127,22 @@ impl Game {
             event_queue: EventQueue::new(),
             data: data,
             score: Score{score: 0, turn:0 },
+            history: History::new(),
+            log: EventLog::new(),
+        }
+    }
+
+    // A copy of the game to try things out on. Undo history and the log aren't copied.
+    pub fn scratch_copy(&self) -> Game {
+        Game {
+            world: self.world.clone(),
+            actors: self.actors.clone(),
+            recordings: self.recordings.clone(),
+            event_queue: self.event_queue.clone(),
+            data: self.data,
+            score: self.score.clone(),
+            history: History::new(),
+            log: EventLog::new(),
         }
     }
 
@@ -133,48 +184,189 @@ impl Game {
         self.world.actors.mut_set(location, &Some(new_actor))
     }
 
+    // Follows control steps from the npc's current step until it reaches an action.
+    // Returns false if the npc jumped past the end of a recording that doesn't loop.
+    // If the step limit is reached, the npc is left on a control step, which then fails to execute.
+    pub fn resolve_control_steps(&self, evt: &mut ActorEvent) -> Result<bool> {
+        for _ in 0..MAX_CONTROL_STEPS {
+            let step = self.recordings.get(evt.recording).at(evt.recording_idx);
+            let next = match step.action {
+                SubAction::Jump(target) => target,
+                SubAction::JumpIf(condition, target) => {
+                    if action::evaluate_condition(condition, evt.actor, step.direction, self)? {
+                        target
+                    } else {
+                        evt.recording_idx + 1
+                    }
+                }
+                SubAction::Repeat(start, count) => {
+                    let idx = evt.recording_idx;
+                    let counter = evt.loop_counters.iter().position(|(step, _)| *step == idx);
+                    let passes = counter.map_or(1, |c| evt.loop_counters[c].1 + 1);
+                    if let Some(c) = counter {
+                        evt.loop_counters.remove(c);
+                    }
+                    if passes < count {
+                        evt.loop_counters.push((idx, passes));
+                        start
+                    } else {
+                        idx + 1
+                    }
+                }
+                SubAction::Call(callee) => {
+                    if self.recordings.get(callee).len() == 0 {
+                        evt.recording_idx + 1
+                    } else if evt.call(callee).is_ok() {
+                        0
+                    } else {
+                        // Stay on the call step, which fails when executed.
+                        return Ok(true);
+                    }
+                }
+                _ => return Ok(true),
+            };
+            if !evt.advance(&self.recordings.recordings, next) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     fn npc_turn(&mut self, mut evt: ActorEvent) -> Result<()> {
+        let actor = evt.actor;
+        if !self.resolve_control_steps(&mut evt)? {
+            let location = *self.world.actors.get_location(&actor)?;
+            let despawns = evt.interval.is_none();
+            let mut update = GameUpdate::new();
+            self.finish_play(evt, &mut update)?;
+            update.apply(self)?;
+            if despawns {
+                self.log_event(Some(actor), location, LogEvent::Finished);
+            }
+            return Ok(());
+        }
         let recording: &Recording = self.recordings.get(evt.recording);
         // handle looping here.
         let action = recording.at(evt.recording_idx);
+        let fallback = recording.fallback_at(evt.recording_idx);
 
-        let action_result = action::execute_action(evt.actor, action, self);
+        // Multi-turn steps only execute on their final turn.
+        if evt.progress + 1 < action::action_duration(evt.actor, action, self)? {
+            evt.progress += 1;
+            evt.last_result = Some(StepResult::InProgress);
+            let mut update = GameUpdate::new();
+            update.eventqueue.next_turn.push_back(evt);
+            return update.apply(self);
+        }
+        evt.progress = 0;
 
-        let mut live = true;
+        let action_result = action::execute_action(evt.actor, action, self);
 
-        match action_result {
+        // The action, its paradox and any despawn are built up as a single update,
+        // so the npc's turn applies all at once or not at all.
+        let (mut update, paradox_increment, outcome, event) = match action_result {
             Ok(update) => {
-                update.apply(self)?;
-                let (update, survivable) = paradox::update_actor_paradox(evt.actor, 0.0, self)?;
-                live = survivable;
-                update.apply(self)?;
+                evt.last_result = Some(StepResult::Done);
+                (update, 0.0, StepOutcome::Advance, self.action_event(actor, action.action)?)
+            }
+            Err(ActionFail(reason)) => {
+                evt.last_result = Some(StepResult::Failed(reason.to_string()));
+                let (update, paradox_increment, outcome) = self.npc_fallback(&mut evt, fallback)?;
+                (update, paradox_increment, outcome, LogEvent::Failed(action.action, reason))
             }
-            Err(ActionFail(_)) => {
-                let (update, survivable) = paradox::update_actor_paradox(evt.actor, 64.0, self)?;
-                live = survivable;
-                update.apply(self)?;
-            } // call fallback action
             Err(res) => Err(res)?,
         };
+        let survives = paradox::update_actor_paradox(actor, paradox_increment, self, &mut update)?;
+        let location = *update.world.actor_updates.get_location(&self.world.actors, &actor)?;
+        let mut removal = (!survives).then_some(LogEvent::ParadoxDeath);
+        let mut finished = false;
 
-        let recording: &Recording = self.recordings.get(evt.recording);
-        evt.recording_idx += 1;
-        if evt.recording_idx >= recording.len() {
-            if recording.should_loop {
-                evt.recording_idx = evt.recording_idx % recording.len();
-            } else {
-                live = false;
+        match outcome {
+            StepOutcome::Advance => {
+                evt.retries = 0;
+                finished = !evt.advance(&self.recordings.recordings, evt.recording_idx + 1);
             }
+            StepOutcome::Repeat => {}
+            StepOutcome::Despawn => removal = removal.or(Some(LogEvent::Despawned)),
         }
-        if live {
-            self.event_queue.next_turn.push_back(evt);
+        if removal.is_some() {
+            devtools::despawn_actor(actor, self, &mut update)?;
+        } else if finished {
+            if evt.interval.is_none() {
+                removal = Some(LogEvent::Finished);
+            }
+            self.finish_play(evt, &mut update)?;
         } else {
-            let update = devtools::despawn_actor(evt.actor, &self)?;
-            update.apply(self).unwrap();
+            update.eventqueue.next_turn.push_back(evt);
+        }
+        update.apply(self)?;
+        self.log_event(Some(actor), location, event);
+        if let Some(removal) = removal {
+            self.log_event(Some(actor), location, removal);
         }
         Ok(())
     }
 
+    pub fn log_event(&mut self, actor: Option<TrackableId>, location: Coordinate, event: LogEvent) {
+        let turn = self.score.turn;
+        self.log.push(LogEntry { turn, actor, location, event });
+    }
+
+    // Describes an action that is about to succeed, while what it drops is still in the inventory.
+    fn action_event(&self, actor: TrackableId, action: SubAction) -> Result<LogEvent> {
+        Ok(match action {
+            SubAction::Move => LogEvent::Moved,
+            SubAction::Craft(recipe) => LogEvent::Crafted(recipe),
+            SubAction::Drop(slot) => {
+                let location = self.world.actors.get_location(&actor)?;
+                let dropper = self.world.actors.get(location)?.ok_or(Error("No actor at expected coordinates"))?;
+                match dropper.inventory.get_items().get(slot) {
+                    Some(Some(item)) => LogEvent::Dropped(item.definition),
+                    _ => LogEvent::Acted(action),
+                }
+            }
+            _ => LogEvent::Acted(action),
+        })
+    }
+
+    // Once an npc's recording has finished, it despawns, unless it was deployed to play again after an interval.
+    fn finish_play(&self, mut evt: ActorEvent, update: &mut GameUpdate) -> Result<()> {
+        let Some(interval) = evt.interval else {
+            return devtools::despawn_actor(evt.actor, self, update);
+        };
+        evt.restart();
+        update.eventqueue.schedule(self.event_queue.turn + i64::from(interval.max(1)), evt);
+        Ok(())
+    }
+
+    // Handles a failed recording step. Returns the update to apply instead, the paradox generated,
+    // and what to do with the recording afterwards.
+    fn npc_fallback(
+        &self,
+        evt: &mut ActorEvent,
+        policy: FallbackPolicy,
+    ) -> Result<(GameUpdate, f64, StepOutcome)> {
+        let failed = (GameUpdate::new(), 64.0, StepOutcome::Advance);
+        match policy {
+            FallbackPolicy::Paradox => Ok(failed),
+            FallbackPolicy::Retry if evt.retries < MAX_RETRIES => {
+                evt.retries += 1;
+                Ok((GameUpdate::new(), 0.0, StepOutcome::Repeat))
+            }
+            FallbackPolicy::Retry => Ok(failed),
+            FallbackPolicy::Wait => Ok((GameUpdate::new(), 0.0, StepOutcome::Repeat)),
+            FallbackPolicy::Skip => Ok((GameUpdate::new(), 0.0, StepOutcome::Advance)),
+            FallbackPolicy::Alternate(alternate) => {
+                match action::execute_action(evt.actor, alternate, self) {
+                    Ok(update) => Ok((update, 0.0, StepOutcome::Advance)),
+                    Err(ActionFail(_)) => Ok(failed),
+                    Err(res) => Err(res),
+                }
+            }
+            FallbackPolicy::Despawn => Ok((GameUpdate::new(), 0.0, StepOutcome::Despawn)),
+        }
+    }
+
     pub fn do_npc_turns(&mut self) -> Result<()> {
         while let Some(evt) = self.event_queue.get_next_event() {
             self.npc_turn(evt)?;
@@ -182,31 +374,121 @@ impl Game {
         Ok(())
     }
 
+    // Every building ticks against the same state, so their updates are merged rather than layered.
+    // A building whose update conflicts with an earlier one (by x, then y) is idle this turn.
+    pub fn do_building_ticks(&mut self) -> Result<()> {
+        let mut update = GameUpdate::new();
+        let mut scored = Vec::new();
+        let dimensions = self.world.dimensions();
+        for x in 0..dimensions.x {
+            for y in 0..dimensions.y {
+                let location = Coordinate { x, y };
+                let Some(building) = self.world.buildings.get(&location)? else {
+                    continue;
+                };
+                let Some(function) = building.definition.on_tick_fn else {
+                    continue;
+                };
+                match function(location, building.facing, self) {
+                    Ok(tick) => {
+                        let score = tick.score.score;
+                        match update.merge(tick) {
+                            Ok(()) if score != 0 => scored.push((location, score)),
+                            Ok(()) | Err(UpdateConflict(_)) => {}
+                            Err(res) => Err(res)?,
+                        }
+                    }
+                    Err(ActionFail(_)) => {}
+                    Err(res) => Err(res)?,
+                }
+            }
+        }
+        update.apply(self)?;
+        for (location, score) in scored {
+            self.log_event(None, location, LogEvent::ScoreGained(score));
+        }
+        Ok(())
+    }
+
     // Process a player's actions.
     pub fn player_action(&mut self, action: action::Action) -> Result<()> {
+        self.recordings.check_room()?;
         let actor_ref = self.actors.get_player()?;
 
         match action::execute_action(actor_ref, action, self) {
-            Ok(update) => {
-                // TODO: add setting to allow recording failed actions.
-                self.recordings.append(action);
+            Ok(mut update) => {
+                let event = self.action_event(actor_ref, action.action)?;
+                let survivable = paradox::update_actor_paradox(actor_ref, 0.0, self, &mut update)?;
                 update.apply(self)?;
-                let (_, survivable) = paradox::update_actor_paradox(actor_ref, 0.0, self)?;
+                let location = *self.get_player_coords()?;
+                self.log_event(Some(actor_ref), location, event);
+                self.recordings.append(action);
                 if survivable {
                     Ok(())
                 } else {
                     Err(Error("You died"))
                 }
             }
+            // Kept for clones to attempt later. The turn passes as if the player waited.
+            Err(ActionFail(reason)) if self.recordings.records_failures() => {
+                let location = *self.get_player_coords()?;
+                self.log_event(Some(actor_ref), location, LogEvent::Failed(action.action, reason));
+                self.recordings.append_failed(action);
+                Ok(())
+            }
             //Err(ActionFail(_)) => Ok(()), // Call fallback action.
             Err(res) => Err(res),
         }
     }
 
+    // A full turn: the player's action followed by every npc.
+    // If anything fails, the game is restored to its state before the turn.
     pub fn player_action_and_turn(&mut self, action: action::Action) -> Result<()> {
+        let snapshot = GameSnapshot::new(self);
+        if let Err(e) = self.take_turn(action) {
+            snapshot.restore(self);
+            // Logged after the rollback, so the entry is kept.
+            let event = match e {
+                ActionFail(reason) => LogEvent::Failed(action.action, reason),
+                Error("You died") => LogEvent::ParadoxDeath,
+                _ => return Err(e),
+            };
+            let player = self.actors.get_player()?;
+            let location = *self.get_player_coords()?;
+            self.log_event(Some(player), location, event);
+            return Err(e);
+        }
+        History::record(self, &snapshot)
+    }
+
+    // Actions that take several turns are checked up front, then the player waits until the
+    // final turn, when the action takes effect. If the world changes during the wait so that the
+    // action fails after all, e.g. a clone steps onto the destination, the waited turns fail with
+    // it and player_action_and_turn rolls all of them back.
+    fn take_turn(&mut self, action: action::Action) -> Result<()> {
+        self.recordings.check_room()?;
+        let player = self.actors.get_player()?;
+        let duration = action::action_duration(player, action, self)?;
+        if duration > 1 {
+            match action::execute_action(player, action, self) {
+                Ok(_) => {}
+                // Recorded failures take as long as the action would have, as they will for clones.
+                Err(ActionFail(_)) if self.recordings.records_failures() => {}
+                Err(e) => return Err(e),
+            }
+            for _ in 1..duration {
+                self.end_turn()?;
+            }
+        }
         self.player_action(action)?;
+        self.end_turn()
+    }
+
+    // Everything that happens after the player acts.
+    pub fn end_turn(&mut self) -> Result<()> {
         self.do_npc_turns()?;
-        self.event_queue.advance_turn()?;
+        self.do_building_ticks()?;
+        self.event_queue.advance_turn(self.score.turn + 1)?;
         self.score.turn += 1;
         paradox::diffuse_paradox(&mut self.world.paradox);
         Ok(())
@@ -215,12 +497,16 @@ impl Game {
 
 #[cfg(test)]
 mod tests {
-    use action::{Action, SubAction};
+    use action::{Action, Condition};
 
     use crate::{
+        buildings::Building,
+        game_state::world::FloorTile,
         devtools,
         direction::{AbsoluteDirection, Direction::Absolute},
+        eventqueue::Deployment,
         inventory::Item,
+        recording::{text, MAX_CALL_DEPTH},
     };
 
     use super::*;
@@ -254,7 +540,7 @@ mod tests {
         game.player_action(actions[0]).unwrap();
         game.player_action(actions[1]).unwrap();
 
-        RecordingModule::end_record(&mut game, false).unwrap();
+        RecordingModule::end_record(&mut game, false, 1, "there and back").unwrap();
          RecordingModule::take_item(&mut game).unwrap();
 
         // This is really ugly. Perhaps recording needs a nicer API.
@@ -288,8 +574,8 @@ mod tests {
 
         let sample_recording_id = game.recordings.recordings.register_recording(Recording {
             command_list: actions,
-            inventory: Default::default(),
             should_loop: true,
+            ..Recording::blank()
         });
         let cloner_def = data.items.get(&"basic_cloner".to_string()).unwrap();
         let new_cloner = Item::new_cloner(cloner_def, sample_recording_id);
@@ -316,4 +602,563 @@ mod tests {
         let dest = game.world.actors.get(&Coordinate { x: 0, y: 2 }).unwrap();
         assert!(dest.is_some());
     }
+
+    #[test]
+    fn multi_turn_actions() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 2, y: 2 }, data);
+        assert!(game.spawn(&Coordinate { x: 0, y: 0 }).is_ok());
+        game.world.floor.mut_set(&Coordinate { x: 0, y: 1 }, &FloorTile::Stone).unwrap();
+        game.world.floor.mut_set(&Coordinate { x: 1, y: 1 }, &FloorTile::Stone).unwrap();
+
+        let north = Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action: SubAction::Move,
+        };
+        game.player_action_and_turn(north).unwrap();
+        assert_eq!(game.score.turn, 2);
+        assert_eq!(*game.get_player_coords().unwrap(), Coordinate { x: 0, y: 1 });
+
+        // A clone following the same step is busy for a turn before it moves.
+        let recording_id = game.recordings.recordings.register_recording(Recording {
+            command_list: vec![north],
+            should_loop: true,
+            ..Recording::blank()
+        });
+        let clone_id = game.world.actors.mut_get_next_id();
+        let clone = Actor::new(data.actors.get("clone").unwrap(), clone_id);
+        game.world.actors.mut_set(&Coordinate { x: 1, y: 0 }, &Some(clone)).unwrap();
+        game.event_queue.this_turn.push_back(ActorEvent::new(clone_id, recording_id));
+
+        game.do_npc_turns().unwrap();
+        assert_eq!(*game.world.actors.get_location(&clone_id).unwrap(), Coordinate { x: 1, y: 0 });
+        assert_eq!(game.event_queue.next_turn[0].progress, 1);
+
+        game.event_queue.advance_turn(game.score.turn + 1).unwrap();
+        game.do_npc_turns().unwrap();
+        assert_eq!(*game.world.actors.get_location(&clone_id).unwrap(), Coordinate { x: 1, y: 1 });
+        assert_eq!(game.event_queue.next_turn[0].progress, 0);
+    }
+
+    #[test]
+    fn multi_turn_action_blocked_during_wait() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 2, y: 2 }, data);
+        assert!(game.spawn(&Coordinate { x: 0, y: 0 }).is_ok());
+        game.world.floor.mut_set(&Coordinate { x: 0, y: 1 }, &FloorTile::Stone).unwrap();
+
+        // A clone halfway through its own move onto the stone gets there first.
+        let recording_id = game.recordings.recordings.register_recording(Recording {
+            command_list: vec![Action {
+                direction: Absolute(AbsoluteDirection::W),
+                action: SubAction::Move,
+            }],
+            should_loop: true,
+            ..Recording::blank()
+        });
+        let clone_id = game.world.actors.mut_get_next_id();
+        let clone = Actor::new(data.actors.get("clone").unwrap(), clone_id);
+        game.world.actors.mut_set(&Coordinate { x: 1, y: 1 }, &Some(clone)).unwrap();
+        let mut evt = ActorEvent::new(clone_id, recording_id);
+        evt.progress = 1;
+        game.event_queue.this_turn.push_back(evt);
+
+        let north = Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action: SubAction::Move,
+        };
+        assert_eq!(game.player_action_and_turn(north), Err(ActionFail("destination occupied")));
+
+        // Every waited turn is rolled back along with the failed move.
+        assert_eq!(game.score.turn, 0);
+        assert_eq!(*game.get_player_coords().unwrap(), Coordinate { x: 0, y: 0 });
+        assert_eq!(*game.world.actors.get_location(&clone_id).unwrap(), Coordinate { x: 1, y: 1 });
+    }
+
+    #[test]
+    fn fallback_policies() {
+        let data = Data::get_test_config();
+        let blocked_clone = |fallback: FallbackPolicy| {
+            // The clone at (0, 0) tries to move onto the player at (0, 1).
+            let mut game = Game::new(Coordinate { x: 2, y: 2 }, data);
+            assert!(game.spawn(&Coordinate { x: 0, y: 1 }).is_ok());
+            let recording_id = game.recordings.recordings.register_recording(Recording {
+                command_list: vec![Action {
+                    direction: Absolute(AbsoluteDirection::N),
+                    action: SubAction::Move,
+                }, Action {
+                    direction: Absolute(AbsoluteDirection::N),
+                    action: SubAction::Wait,
+                }],
+                should_loop: true,
+                fallback,
+                ..Recording::blank()
+            });
+            let clone_id = game.world.actors.mut_get_next_id();
+            let clone = Actor::new(data.actors.get("clone").unwrap(), clone_id);
+            game.world.actors.mut_set(&Coordinate { x: 0, y: 0 }, &Some(clone)).unwrap();
+            game.event_queue.this_turn.push_back(ActorEvent::new(clone_id, recording_id));
+            game.do_npc_turns().unwrap();
+            game
+        };
+        let paradox_at_clone =
+            |game: &Game| game.world.paradox.get(&Coordinate { x: 0, y: 0 }).unwrap().0;
+
+        let game = blocked_clone(FallbackPolicy::Paradox);
+        assert_eq!(paradox_at_clone(&game), 64.0);
+        assert_eq!(game.event_queue.next_turn[0].recording_idx, 1);
+
+        let game = blocked_clone(FallbackPolicy::Wait);
+        assert_eq!(paradox_at_clone(&game), 0.0);
+        assert_eq!(game.event_queue.next_turn[0].recording_idx, 0);
+
+        let game = blocked_clone(FallbackPolicy::Retry);
+        assert_eq!(paradox_at_clone(&game), 0.0);
+        assert_eq!(game.event_queue.next_turn[0].retries, 1);
+
+        let game = blocked_clone(FallbackPolicy::Skip);
+        assert_eq!(paradox_at_clone(&game), 0.0);
+        assert_eq!(game.event_queue.next_turn[0].recording_idx, 1);
+
+        let game = blocked_clone(FallbackPolicy::Despawn);
+        assert_eq!(paradox_at_clone(&game), 0.0);
+        assert!(game.event_queue.next_turn.is_empty());
+        assert!(game.world.actors.get(&Coordinate { x: 0, y: 0 }).unwrap().is_none());
+
+        let go_around = FallbackPolicy::Alternate(Action {
+            direction: Absolute(AbsoluteDirection::E),
+            action: SubAction::Move,
+        });
+        let game = blocked_clone(go_around);
+        assert_eq!(paradox_at_clone(&game), 0.0);
+        assert!(game.world.actors.get(&Coordinate { x: 1, y: 0 }).unwrap().is_some());
+    }
+
+    #[test]
+    fn conditional_steps() {
+        let data = Data::get_test_config();
+        let ore_def = data.items.get("raw_crystal").unwrap();
+        let step = |action| Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action,
+        };
+        let spawn_clone = |game: &mut Game, command_list, should_loop| {
+            let recording_id = game.recordings.recordings.register_recording(Recording {
+                command_list,
+                should_loop,
+                ..Recording::blank()
+            });
+            let clone_id = game.world.actors.mut_get_next_id();
+            let clone = Actor::new(data.actors.get("clone").unwrap(), clone_id);
+            game.world.actors.mut_set(&Coordinate { x: 0, y: 0 }, &Some(clone)).unwrap();
+            game.event_queue.this_turn.push_back(ActorEvent::new(clone_id, recording_id));
+        };
+
+        // Take the ore if there is any, otherwise wait.
+        let mut game = Game::new(Coordinate { x: 1, y: 1 }, data);
+        game.world.items.mut_set(&Coordinate { x: 0, y: 0 }, &[Some(Item::new(ore_def, 1))]).unwrap();
+        let take_ore = vec![
+            step(SubAction::JumpIf(Condition::FloorItemIs(ore_def), 2)),
+            step(SubAction::Wait),
+            step(SubAction::Take),
+        ];
+        spawn_clone(&mut game, take_ore, true);
+        game.do_npc_turns().unwrap();
+        assert!(game.world.items.get(&Coordinate { x: 0, y: 0 }).unwrap()[0].is_none());
+        assert_eq!(game.event_queue.next_turn[0].recording_idx, 0);
+        game.event_queue.advance_turn(game.score.turn + 1).unwrap();
+        game.do_npc_turns().unwrap();
+        assert_eq!(game.event_queue.next_turn[0].recording_idx, 2);
+
+        // A jump loop with no actions fails the step instead of hanging.
+        let mut game = Game::new(Coordinate { x: 1, y: 1 }, data);
+        spawn_clone(&mut game, vec![step(SubAction::Jump(0))], true);
+        game.do_npc_turns().unwrap();
+        assert_eq!(game.world.paradox.get(&Coordinate { x: 0, y: 0 }).unwrap().0, 64.0);
+
+        // Jumping past the end of a recording that doesn't loop ends it.
+        let mut game = Game::new(Coordinate { x: 1, y: 1 }, data);
+        spawn_clone(&mut game, vec![step(SubAction::Jump(5)), step(SubAction::Wait)], false);
+        game.do_npc_turns().unwrap();
+        assert!(game.world.actors.get(&Coordinate { x: 0, y: 0 }).unwrap().is_none());
+    }
+
+    #[test]
+    fn repeats() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 1 }, data);
+        let step = |action| Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action,
+        };
+
+        // Steps 0-1 run three times, then step 3 runs. The whole recording plays twice.
+        let recording_id = game.recordings.recordings.register_recording(Recording {
+            command_list: vec![
+                step(SubAction::Wait),
+                step(SubAction::Wait),
+                step(SubAction::Repeat(0, 3)),
+                step(SubAction::Wait),
+            ],
+            repeat_count: 2,
+            ..Recording::blank()
+        });
+        let clone_id = game.world.actors.mut_get_next_id();
+        let clone = Actor::new(data.actors.get("clone").unwrap(), clone_id);
+        game.world.actors.mut_set(&Coordinate { x: 0, y: 0 }, &Some(clone)).unwrap();
+        game.event_queue.this_turn.push_back(ActorEvent::new(clone_id, recording_id));
+
+        // Control steps are resolved at the start of a turn, so a clone can be left pointing at one.
+        let mut visited = Vec::new();
+        while let Some(evt) = game.event_queue.this_turn.front() {
+            visited.push(evt.recording_idx);
+            game.do_npc_turns().unwrap();
+            game.event_queue.advance_turn(game.score.turn + 1).unwrap();
+        }
+        let play = [0, 1, 2, 1, 2, 1, 2];
+        assert_eq!(visited, [play, play].concat());
+        assert!(game.world.actors.get(&Coordinate { x: 0, y: 0 }).unwrap().is_none());
+    }
+
+    #[test]
+    fn sub_recordings() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 1 }, data);
+        let step = |action| Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action,
+        };
+
+        let callee = game.recordings.recordings.register_recording(Recording {
+            command_list: vec![step(SubAction::Wait), step(SubAction::Wait)],
+            // Called recordings play once, even if they loop on their own.
+            should_loop: true,
+            ..Recording::blank()
+        });
+        let caller = game.recordings.recordings.register_recording(Recording {
+            command_list: vec![
+                step(SubAction::Wait),
+                step(SubAction::Call(callee)),
+                step(SubAction::Wait),
+            ],
+            ..Recording::blank()
+        });
+        let clone_id = game.world.actors.mut_get_next_id();
+        let clone = Actor::new(data.actors.get("clone").unwrap(), clone_id);
+        game.world.actors.mut_set(&Coordinate { x: 0, y: 0 }, &Some(clone)).unwrap();
+        game.event_queue.this_turn.push_back(ActorEvent::new(clone_id, caller));
+
+        let mut visited = Vec::new();
+        while let Some(evt) = game.event_queue.this_turn.front() {
+            visited.push((evt.recording == callee, evt.recording_idx));
+            game.do_npc_turns().unwrap();
+            game.event_queue.advance_turn(game.score.turn + 1).unwrap();
+        }
+        assert_eq!(visited, [(false, 0), (false, 1), (true, 1), (false, 2)]);
+        assert!(game.world.actors.get(&Coordinate { x: 0, y: 0 }).unwrap().is_none());
+
+        // Recursion and overly deep nesting are refused.
+        let mut evt = ActorEvent::new(clone_id, caller);
+        assert!(evt.call(caller).is_err());
+        evt.call(callee).unwrap();
+        assert!(evt.call(caller).is_err());
+        let mut evt = ActorEvent::new(clone_id, caller);
+        for _ in 0..MAX_CALL_DEPTH {
+            let blank = game.recordings.recordings.register_recording(Recording::blank());
+            evt.call(blank).unwrap();
+        }
+        let blank = game.recordings.recordings.register_recording(Recording::blank());
+        assert!(evt.call(blank).is_err());
+    }
+
+    #[test]
+    fn named_recordings() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 1 }, data);
+        game.spawn(&Coordinate { x: 0, y: 0 }).unwrap();
+        let player = game.get_player_actor().unwrap().actor_id;
+        let recorder = Item::new(data.items.get("recorder").unwrap(), 1);
+
+        for name in ["patrol", "  "] {
+            let location = *game.get_player_coords().unwrap();
+            devtools::grant_item(recorder, location, &game).unwrap().apply(&mut game).unwrap();
+            RecordingModule::init_record(&mut game, 0).unwrap();
+            RecordingModule::end_record(&mut game, false, 1, name).unwrap();
+        }
+        let names: Vec<&str> = game.recordings.recordings.get_all().iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["", "patrol", "Recording 2"]);
+        assert_eq!(game.recordings.recordings.get_all()[1].creator, Some(player));
+    }
+
+    #[test]
+    fn recorder_tiers() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 1 }, data);
+        game.spawn(&Coordinate { x: 0, y: 0 }).unwrap();
+        let location = *game.get_player_coords().unwrap();
+        let wait = Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action: SubAction::Wait,
+        };
+
+        let recorder = Item::new(data.items.get("recorder").unwrap(), 1);
+        devtools::grant_item(recorder, location, &game).unwrap().apply(&mut game).unwrap();
+        RecordingModule::init_record(&mut game, 0).unwrap();
+        assert!(RecordingModule::end_record(&mut game, true, 1, "").is_err());
+        // A full recording refuses further actions, so it can always be ended.
+        let max_steps = data.items["recorder"].recorder.as_ref().unwrap().max_steps.unwrap();
+        for _ in 0..max_steps {
+            game.player_action_and_turn(wait).unwrap();
+        }
+        assert_eq!(
+            game.player_action_and_turn(wait),
+            Err(ActionFail("recording is full, end it first"))
+        );
+        assert_eq!(game.score.turn, max_steps as i64);
+        RecordingModule::end_record(&mut game, false, 1, "").unwrap();
+
+        let recorder = Item::new(data.items.get("looping_recorder").unwrap(), 1);
+        devtools::grant_item(recorder, location, &game).unwrap().apply(&mut game).unwrap();
+        RecordingModule::init_record(&mut game, 0).unwrap();
+        game.player_action(wait).unwrap();
+        RecordingModule::end_record(&mut game, true, 1, "").unwrap();
+
+        // Items that aren't recorders can't start a recording.
+        let crystal = Item::new(data.items.get("echo_crystal").unwrap(), 1);
+        devtools::grant_item(crystal, location, &game).unwrap().apply(&mut game).unwrap();
+        assert!(RecordingModule::init_record(&mut game, 0).is_err());
+    }
+
+    #[test]
+    fn record_failed_actions() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 1 }, data);
+        game.spawn(&Coordinate { x: 0, y: 0 }).unwrap();
+        let location = *game.get_player_coords().unwrap();
+        let take = Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action: SubAction::Take,
+        };
+
+        // The basic recorder can't keep failed actions.
+        let recorder = Item::new(data.items.get("recorder").unwrap(), 1);
+        devtools::grant_item(recorder, location, &game).unwrap().apply(&mut game).unwrap();
+        RecordingModule::init_record(&mut game, 0).unwrap();
+        assert!(RecordingModule::set_record_failures(&mut game, true).is_err());
+        assert!(game.player_action_and_turn(take).is_err());
+        assert_eq!(game.score.turn, 0);
+        assert_eq!(game.recordings.current_recording.as_ref().unwrap().len(), 0);
+        game.recordings.current_recording = None;
+
+        let recorder = Item::new(data.items.get("temporal_recorder").unwrap(), 1);
+        devtools::grant_item(recorder, location, &game).unwrap().apply(&mut game).unwrap();
+        RecordingModule::init_record(&mut game, 0).unwrap();
+        RecordingModule::set_record_failures(&mut game, true).unwrap();
+        game.player_action_and_turn(take).unwrap();
+        assert_eq!(game.score.turn, 1);
+        let recording = game.recordings.current_recording.as_ref().unwrap();
+        assert_eq!(recording.at(0).action, SubAction::Take);
+    }
+
+    #[test]
+    fn duplicate_recording() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 1 }, data);
+        game.spawn(&Coordinate { x: 0, y: 0 }).unwrap();
+        let location = *game.get_player_coords().unwrap();
+        let crystal = Item::new(data.items.get("echo_crystal").unwrap(), 1);
+        devtools::grant_item(crystal, location, &game).unwrap().apply(&mut game).unwrap();
+
+        let id = game.recordings.recordings.register_recording(Recording {
+            command_list: vec![Action {
+                direction: Absolute(AbsoluteDirection::N),
+                action: SubAction::Wait,
+            }],
+            ..Recording::blank()
+        });
+        RecordingModule::duplicate(&mut game, id).unwrap();
+        assert_eq!(game.recordings.temp_item.unwrap().recording, Some(id));
+        let inventory = game.get_player_actor().unwrap().inventory;
+        assert!(inventory.get_items().iter().all(|i| i.is_none()));
+
+        // The crystal has been spent, and edited copies cost the same.
+        RecordingModule::take_item(&mut game).unwrap();
+        game.recordings.temp_item = None;
+        assert!(RecordingModule::duplicate(&mut game, id).is_err());
+        let copy = game.recordings.get(id).clone();
+        assert!(RecordingModule::save_new(&mut game, copy.clone()).is_err());
+        devtools::grant_item(crystal, location, &game).unwrap().apply(&mut game).unwrap();
+        RecordingModule::save_new(&mut game, copy).unwrap();
+        assert!(game.recordings.temp_item.is_some());
+    }
+
+    #[test]
+    fn scheduled_deployment() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 3 }, data);
+        game.spawn(&Coordinate { x: 0, y: 0 }).unwrap();
+        let id = game.recordings.recordings.register_recording(Recording {
+            command_list: vec![Action {
+                direction: Absolute(AbsoluteDirection::N),
+                action: SubAction::Wait,
+            }],
+            ..Recording::blank()
+        });
+        let cloner = Item::new_cloner(data.items.get("basic_cloner").unwrap(), id);
+        devtools::grant_item(cloner, Coordinate { x: 0, y: 0 }, &game).unwrap().apply(&mut game).unwrap();
+        assert!(RecordingModule::set_deployment(&mut game, 1, Deployment::default()).is_err());
+        let deployment = Deployment { delay: 2, interval: Some(3) };
+        RecordingModule::set_deployment(&mut game, 0, deployment).unwrap();
+
+        game.player_action_and_turn(Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action: SubAction::Use(0),
+        })
+        .unwrap();
+        let clone_id = game.world.actors.get(&Coordinate { x: 0, y: 1 }).unwrap().unwrap().actor_id;
+        let wait = Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action: SubAction::Wait,
+        };
+        // Deployed on turn 0, first acting on turn 2, then every third turn after finishing.
+        let mut acted = Vec::new();
+        for _ in 0..9 {
+            let turn = game.score.turn;
+            if game.event_queue.this_turn.iter().any(|e| e.actor == clone_id) {
+                acted.push(turn);
+            }
+            game.player_action_and_turn(wait).unwrap();
+        }
+        assert_eq!(acted, [2, 5, 8]);
+        assert!(game.world.actors.get_location(&clone_id).is_ok());
+    }
+
+    #[test]
+    fn turn_log() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 3 }, data);
+        game.spawn(&Coordinate { x: 0, y: 0 }).unwrap();
+        let north = |action| Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action,
+        };
+        let id = game.recordings.recordings.register_recording(Recording {
+            command_list: vec![north(SubAction::Move), north(SubAction::Move)],
+            ..Recording::blank()
+        });
+        let cloner = Item::new_cloner(data.items.get("basic_cloner").unwrap(), id);
+        devtools::grant_item(cloner, Coordinate { x: 0, y: 0 }, &game).unwrap().apply(&mut game).unwrap();
+        game.player_action_and_turn(north(SubAction::Use(0))).unwrap();
+        game.player_action_and_turn(north(SubAction::Wait)).unwrap();
+        let south = Action {
+            direction: Absolute(AbsoluteDirection::S),
+            action: SubAction::Move,
+        };
+        assert!(game.player_action_and_turn(south).is_err());
+
+        let player = game.actors.get_player().unwrap();
+        let clone = TrackableId(player.0 + 1);
+        let entries: Vec<(i64, Option<TrackableId>, Coordinate, LogEvent)> = game
+            .log
+            .entries()
+            .map(|e| (e.turn, e.actor, e.location, e.event.clone()))
+            .collect();
+        let (origin, top) = (Coordinate { x: 0, y: 0 }, Coordinate { x: 0, y: 2 });
+        assert_eq!(
+            entries,
+            [
+                (0, Some(player), origin, LogEvent::Acted(SubAction::Use(0))),
+                (0, Some(clone), top, LogEvent::Moved),
+                (1, Some(player), origin, LogEvent::Acted(SubAction::Wait)),
+                (1, Some(clone), top, LogEvent::Failed(SubAction::Move, "destination out of bounds")),
+                (1, Some(clone), top, LogEvent::Finished),
+                (2, Some(player), origin, LogEvent::Failed(SubAction::Move, "destination out of bounds")),
+            ]
+        );
+
+        // Undone turns are forgotten.
+        History::undo(&mut game).unwrap();
+        assert!(game.log.entries().all(|e| e.turn < 1));
+    }
+
+    #[test]
+    fn import_recording() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 1 }, data);
+        game.spawn(&Coordinate { x: 0, y: 0 }).unwrap();
+        let location = *game.get_player_coords().unwrap();
+        let grant = |game: &mut Game, name: &str| {
+            let item = Item::new(data.items.get(name).unwrap(), 1);
+            devtools::grant_item(item, location, game).unwrap().apply(game).unwrap();
+        };
+        let text = "recorder recorder\ninventory raw_crystal\nmove\n";
+        let recording = text::from_text(text, &game.recordings.recordings, data).unwrap();
+        assert!(RecordingModule::import(&mut game, recording.clone()).is_err());
+
+        // Without an echo crystal to pay for the cloner, nothing is taken.
+        grant(&mut game, "recorder");
+        grant(&mut game, "raw_crystal");
+        assert!(RecordingModule::import(&mut game, recording.clone()).is_err());
+        let held = |game: &Game| game.get_player_actor().unwrap().inventory.get_items().iter().flatten().count();
+        assert_eq!(held(&game), 2);
+
+        grant(&mut game, "echo_crystal");
+        RecordingModule::import(&mut game, recording).unwrap();
+        assert_eq!(held(&game), 1);
+        assert!(game.recordings.temp_item.is_some());
+    }
+
+    #[test]
+    fn building_ticks() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 3, y: 1 }, data);
+
+        for (x, name) in [(0, "crystal_deposit"), (1, "auto_miner"), (2, "smelter")] {
+            let definition = data.buildings.get(name).unwrap();
+            game.world
+                .buildings
+                .mut_set(&Coordinate { x, y: 0 }, &Some(Building::new(definition, AbsoluteDirection::E)))
+                .unwrap();
+        }
+        let ore = Item::new(data.items.get("raw_crystal").unwrap(), 1);
+        game.world.items.mut_set(&Coordinate { x: 2, y: 0 }, &[Some(ore)]).unwrap();
+
+        game.do_building_ticks().unwrap();
+        let mined = game.world.items.get(&Coordinate { x: 1, y: 0 }).unwrap()[0].unwrap();
+        let smelted = game.world.items.get(&Coordinate { x: 2, y: 0 }).unwrap()[0].unwrap();
+        assert_eq!(mined.definition.name, "raw_crystal");
+        assert_eq!(smelted.definition.name, "echo_crystal");
+
+        // Both buildings are now idle: the miner's output is full and the smelter has no ore.
+        game.do_building_ticks().unwrap();
+        assert_eq!(game.world.items.get(&Coordinate { x: 1, y: 0 }).unwrap()[0], Some(mined));
+    }
+
+    #[test]
+    fn conveyors() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 3, y: 2 }, data);
+        let conveyor = data.buildings.get("conveyor").unwrap();
+        let ore = Item::new(data.items.get("raw_crystal").unwrap(), 1);
+
+        // Two conveyors feed (1, 0): one from the west and one from the north.
+        let belts = [((0, 0), AbsoluteDirection::E), ((1, 1), AbsoluteDirection::S)];
+        for ((x, y), facing) in belts {
+            let location = Coordinate { x, y };
+            game.world
+                .buildings
+                .mut_set(&location, &Some(Building::new(conveyor, facing)))
+                .unwrap();
+            game.world.items.mut_set(&location, &[Some(ore)]).unwrap();
+        }
+
+        game.do_building_ticks().unwrap();
+        assert_eq!(game.world.items.get(&Coordinate { x: 0, y: 0 }).unwrap()[0], None);
+        assert_eq!(game.world.items.get(&Coordinate { x: 1, y: 0 }).unwrap()[0], Some(ore));
+        assert_eq!(game.world.items.get(&Coordinate { x: 1, y: 1 }).unwrap()[0], Some(ore));
+
+        // The output is now occupied, so the second conveyor stays blocked.
+        game.do_building_ticks().unwrap();
+        assert_eq!(game.world.items.get(&Coordinate { x: 1, y: 1 }).unwrap()[0], Some(ore));
+    }
 }
@@ -1,7 +1,7 @@
 //! Datastructures to represent spatial data (e.g. a map of the world).
 
 use crate::engine::tracking_worldlayer::{TrackableWorldLayer, TrackableWorldLayerDelta};
-use crate::engine::update::{Updatable, Delta, UpdatableContainer};
+use crate::engine::update::{Updatable, Delta, MergeableDelta, UpdatableContainer};
 use crate::engine::worldlayer::{WorldLayer, WorldLayerDelta};
 use crate::error::Result;
 use crate::inventory::Item;
@@ -11,16 +11,27 @@ use crate::{
     datatypes::Coordinate,
 };
 use crate::buildings::Building;
+use serde_derive::{Deserialize, Serialize};
 
 pub type FloorInventory = [Option<Item>; 1];
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum FloorTile {
     Dirt,
     Water,
     Stone
 }
 
+impl FloorTile {
+    // Turns taken to move onto the tile.
+    pub fn move_cost(&self) -> u32 {
+        match self {
+            FloorTile::Stone => 2,
+            FloorTile::Dirt | FloorTile::Water => 1,
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct WorldCell<'a> {
     pub actor: Option<&'a Actor>,
@@ -30,6 +41,7 @@ pub struct WorldCell<'a> {
     pub floor: &'a FloorTile
 }
 
+#[derive(Clone)]
 pub struct World {
     dimensions: Coordinate,
     pub actors: TrackableWorldLayer<Option<Actor>>,
@@ -96,6 +108,7 @@ impl Delta for WorldUpdate {
     }
 
     fn apply(&self, target: &mut World) -> Result<()> {
+        self.validate(target)?;
         self.actor_updates.apply(&mut target.actors)?;
         self.building_updates.apply(&mut target.buildings)?;
         self.item_updates.apply(&mut target.items)?;
@@ -103,12 +116,40 @@ impl Delta for WorldUpdate {
         self.floor_updates.apply(&mut target.floor)?;
         Ok(())
     }
+
+    fn validate(&self, target: &World) -> Result<()> {
+        self.actor_updates.validate(&target.actors)?;
+        self.building_updates.validate(&target.buildings)?;
+        self.item_updates.validate(&target.items)?;
+        self.paradox_updates.validate(&target.paradox)?;
+        self.floor_updates.validate(&target.floor)
+    }
+}
+
+impl MergeableDelta for WorldUpdate {
+    fn check_conflicts(&self, other: &Self) -> Result<()> {
+        self.actor_updates.check_conflicts(&other.actor_updates)?;
+        self.building_updates.check_conflicts(&other.building_updates)?;
+        // Items are moved, not just written: two updates that both put an item down
+        // on the same cell would only leave one of them there.
+        self.item_updates.check_disjoint(&other.item_updates)?;
+        self.paradox_updates.check_conflicts(&other.paradox_updates)?;
+        self.floor_updates.check_conflicts(&other.floor_updates)
+    }
+
+    fn absorb(&mut self, other: Self) {
+        self.actor_updates.absorb(other.actor_updates);
+        self.building_updates.absorb(other.building_updates);
+        self.item_updates.absorb(other.item_updates);
+        self.paradox_updates.absorb(other.paradox_updates);
+        self.floor_updates.absorb(other.floor_updates);
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use crate::{engine::{tracking_worldlayer::TrackableId, update::UpdatableContainerDelta}, static_data::Data};
+    use crate::{engine::{tracking_worldlayer::TrackableId, update::UpdatableContainerDelta}, error::Status, static_data::Data};
 
     use super::*;
 
@@ -154,4 +195,69 @@ mod tests {
         assert!(w.actors.get(&Coordinate { x: 0, y: 0 }).unwrap().is_some());
     }
 
+    #[test]
+    fn merge() {
+        let location = Coordinate { x: 0, y: 0 };
+        let mut first = WorldUpdate::new();
+        let mut second = WorldUpdate::new();
+        first.floor_updates.set(&location, &FloorTile::Stone).unwrap();
+        second.floor_updates.set(&location, &FloorTile::Stone).unwrap();
+        second.paradox_updates.set(&location, &Paradox(1.0)).unwrap();
+
+        // Identical writes to the same cell are not a conflict.
+        first.merge(second).unwrap();
+
+        let mut w = World::new(Coordinate { x: 1, y: 1 });
+        first.apply(&mut w).unwrap();
+        assert_eq!(*w.floor.get(&location).unwrap(), FloorTile::Stone);
+        assert_eq!(*w.paradox.get(&location).unwrap(), Paradox(1.0));
+    }
+
+    #[test]
+    fn merge_conflict() {
+        let location = Coordinate { x: 0, y: 0 };
+        let mut first = WorldUpdate::new();
+        let mut second = WorldUpdate::new();
+        first.floor_updates.set(&location, &FloorTile::Stone).unwrap();
+        second.floor_updates.set(&location, &FloorTile::Water).unwrap();
+
+        assert_eq!(
+            first.merge(second),
+            Err(Status::UpdateConflict("conflicting writes to the same cell"))
+        );
+        assert_eq!(*first.floor_updates.get_cached(&location).unwrap().unwrap(), FloorTile::Stone);
+    }
+
+    #[test]
+    fn merge_item_conflict() {
+        let data = Data::get_test_config();
+        let location = Coordinate { x: 0, y: 0 };
+        let items = [Some(Item::new(data.items.get("raw_crystal").unwrap(), 1))];
+        let mut first = WorldUpdate::new();
+        let mut second = WorldUpdate::new();
+        first.item_updates.set(&location, &items).unwrap();
+        second.item_updates.set(&location, &items).unwrap();
+
+        assert_eq!(
+            first.merge(second),
+            Err(Status::UpdateConflict("two writes to the same cell"))
+        );
+    }
+
+    #[test]
+    fn invalid_update_is_not_applied() {
+        let mut w = World::new(Coordinate { x: 1, y: 1 });
+        let location = Coordinate { x: 0, y: 0 };
+
+        let mut update = WorldUpdate::new();
+        update.floor_updates.set(&location, &FloorTile::Stone).unwrap();
+        update.floor_updates.set(&Coordinate { x: 1, y: 1 }, &FloorTile::Stone).unwrap();
+        assert_eq!(update.apply(&mut w), Err(Status::OutOfBounds));
+        assert_eq!(*w.floor.get(&location).unwrap(), FloorTile::Dirt);
+
+        let mut update = WorldUpdate::new();
+        let id = update.actor_updates.get_next_id(&w.actors);
+        update.actor_updates.remove(id);
+        assert!(update.apply(&mut w).is_err());
+    }
 }
@@ -0,0 +1,256 @@
+//! Runs the simulation without a window, for scripting and benchmarking factory designs.
+//!
+//! Usage: clone-factory --headless [--load SAVE_FILE] [--actions ACTION_FILE] [--turns N] [--log]
+//!
+//! The game starts from the save file if given, otherwise from worldgen. Player actions are read
+//! from a JSON list in the save file action format. If more turns are requested than there are
+//! actions, the player waits for the remainder. The run stops on exactly the requested turn: an
+//! action that would take the player past it is replaced with waiting. With --log, everything in the turn log is printed
+//! as it happens.
+
+use std::fs;
+
+use crate::action::{self, Action, SubAction};
+use crate::datatypes::Coordinate;
+use crate::direction::{Direction, RelativeDirection};
+use crate::error::{Result, Status::{ActionFail, Error}};
+use crate::game_state::game::Game;
+use crate::static_data::Data;
+use crate::{save, worldgen};
+
+pub struct HeadlessOptions {
+    pub load: Option<String>,
+    pub actions: Option<String>,
+    pub turns: Option<usize>,
+    pub log: bool,
+}
+
+impl HeadlessOptions {
+    pub fn parse(args: &[String]) -> Result<HeadlessOptions> {
+        let mut options = HeadlessOptions {
+            load: None,
+            actions: None,
+            turns: None,
+            log: false,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => {}
+                "--load" => {
+                    let path = args.next().ok_or(Error("--load requires a value"))?;
+                    options.load = Some(path.clone());
+                }
+                "--actions" => {
+                    let path = args.next().ok_or(Error("--actions requires a value"))?;
+                    options.actions = Some(path.clone());
+                }
+                "--log" => options.log = true,
+                "--turns" => {
+                    let turns = args.next().ok_or(Error("--turns requires a value"))?;
+                    options.turns = Some(turns.parse().map_err(|_| Error("invalid turn count"))?);
+                }
+                _ => return Err(Error("unrecognized argument")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub struct Report {
+    pub score: i64,
+    pub turn: i64,
+    pub failed_actions: usize,
+    pub player_alive: bool,
+    pub clones: usize,
+    // Clones not due to act until after the next turn.
+    pub waiting_clones: usize,
+    pub queued_events: usize,
+    pub total_paradox: f64,
+    pub max_paradox: f64,
+    pub mean_paradox: f64,
+}
+
+impl Report {
+    pub fn new(game: &Game, failed_actions: usize, player_alive: bool) -> Result<Report> {
+        let dimensions = game.world.dimensions();
+        let mut clones = 0;
+        let mut waiting_clones = 0;
+        let mut total_paradox = 0.0;
+        let mut max_paradox: f64 = 0.0;
+
+        for x in 0..dimensions.x {
+            for y in 0..dimensions.y {
+                let cell = game.world.get_cell(&Coordinate { x, y })?;
+                if let Some(clone) = cell.actor.filter(|a| a.descriptor.name == "clone") {
+                    clones += 1;
+                    let next = game.event_queue.next_for(clone.actor_id);
+                    if next.is_some_and(|(turn, _)| turn > game.event_queue.turn + 1) {
+                        waiting_clones += 1;
+                    }
+                }
+                total_paradox += cell.paradox.0;
+                max_paradox = max_paradox.max(cell.paradox.0);
+            }
+        }
+
+        Ok(Report {
+            score: game.score.score,
+            turn: game.score.turn,
+            failed_actions,
+            player_alive,
+            clones,
+            waiting_clones,
+            queued_events: game.event_queue.queued().count(),
+            total_paradox,
+            max_paradox,
+            mean_paradox: total_paradox / (dimensions.x * dimensions.y) as f64,
+        })
+    }
+
+    pub fn print(&self) {
+        println!("score: {}", self.score);
+        println!("turn: {}", self.turn);
+        println!("failed player actions: {}", self.failed_actions);
+        println!("player alive: {}", self.player_alive);
+        println!("surviving clones: {} ({} waiting)", self.clones, self.waiting_clones);
+        println!("queued events: {}", self.queued_events);
+        println!(
+            "paradox: total {:.1}, max {:.1}, mean {:.3}",
+            self.total_paradox, self.max_paradox, self.mean_paradox
+        );
+    }
+}
+
+const WAIT: Action = Action {
+    direction: Direction::Relative(RelativeDirection::F),
+    action: SubAction::Wait,
+};
+
+// Runs the player's actions, then waits out any remaining turns.
+// Failed actions are logged and replaced with a wait so that the turn count stays fixed.
+pub fn simulate(game: &mut Game, actions: &[Action], turns: usize, print_log: bool) -> Result<Report> {
+    let mut failed_actions = 0;
+    let mut player_alive = true;
+
+    // Some actions take several turns, so count game turns rather than actions.
+    let end = game.score.turn + turns as i64;
+    let mut actions = actions.iter();
+    while game.score.turn < end {
+        let mut action = actions.next().copied().unwrap_or(WAIT);
+        let start = game.score.turn;
+        let player = game.actors.get_player()?;
+        if action::action_duration(player, action, game).is_ok_and(|d| start + d as i64 > end) {
+            println!("turn {}: {:?} would end after the last turn", start, action.action);
+            action = WAIT;
+        }
+        let result = match game.player_action_and_turn(action) {
+            Err(ActionFail(reason)) => {
+                println!("turn {}: {:?} failed: {}", game.score.turn, action.action, reason);
+                failed_actions += 1;
+                game.player_action_and_turn(WAIT)
+            }
+            res => res,
+        };
+        if print_log {
+            for entry in game.log.since(start) {
+                println!("{}", entry);
+            }
+        }
+        match result {
+            Ok(()) => {}
+            Err(Error("You died")) => {
+                println!("turn {}: player died", game.score.turn);
+                player_alive = false;
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Report::new(game, failed_actions, player_alive)
+}
+
+pub fn run(args: &[String]) -> Result<()> {
+    let options = HeadlessOptions::parse(args)?;
+    let data = Data::get_config();
+
+    let mut game = match &options.load {
+        Some(path) => save::load_from_file(path, data)?,
+        None => worldgen::generate_game(data),
+    };
+
+    let actions = match &options.actions {
+        Some(path) => {
+            let contents =
+                fs::read_to_string(path).map_err(|_| Error("unable to read action file"))?;
+            save::load_actions(&contents, data)?
+        }
+        None => Vec::new(),
+    };
+    let turns = options.turns.unwrap_or(actions.len());
+
+    let report = simulate(&mut game, &actions, turns, options.log)?;
+    report.print();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::direction::AbsoluteDirection;
+    use crate::engine::update::UpdatableContainer;
+    use crate::eventlog::LogEvent;
+    use crate::game_state::world::FloorTile;
+
+    use super::*;
+
+    #[test]
+    fn waits_out_turns() {
+        let data = Data::get_test_config();
+        let mut game = worldgen::generate_game(data);
+
+        let actions = [Action {
+            direction: Direction::Relative(RelativeDirection::F),
+            action: SubAction::Take,
+        }];
+        let report = simulate(&mut game, &actions, 5, false).unwrap();
+
+        assert_eq!(report.turn, 5);
+        assert_eq!(report.failed_actions, 1);
+        assert!(report.player_alive);
+        let failure = game.log.entries().find(|e| matches!(e.event, LogEvent::Failed(..))).unwrap();
+        assert_eq!(failure.event, LogEvent::Failed(SubAction::Take, "no item to take"));
+        assert_eq!(failure.turn, 0);
+    }
+
+    #[test]
+    fn parse_options() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+        let options = HeadlessOptions::parse(&args(&["--headless", "--load", "save.json", "--turns", "3"])).unwrap();
+        assert_eq!(options.load.as_deref(), Some("save.json"));
+        assert_eq!(options.turns, Some(3));
+
+        // Flags missing their value are errors rather than being ignored.
+        for flag in ["--load", "--actions", "--turns"] {
+            assert!(HeadlessOptions::parse(&args(&["--headless", flag])).is_err());
+        }
+    }
+
+    #[test]
+    fn stops_on_last_turn() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 2, y: 2 }, data);
+        game.spawn(&Coordinate { x: 0, y: 0 }).unwrap();
+        game.world.floor.mut_set(&Coordinate { x: 0, y: 1 }, &FloorTile::Stone).unwrap();
+
+        // Moving onto stone takes two turns, one more than is left.
+        let actions = [Action {
+            direction: Direction::Absolute(AbsoluteDirection::N),
+            action: SubAction::Move,
+        }];
+        let report = simulate(&mut game, &actions, 1, false).unwrap();
+
+        assert_eq!(report.turn, 1);
+        assert_eq!(*game.get_player_coords().unwrap(), Coordinate { x: 0, y: 0 });
+    }
+}
@@ -0,0 +1,329 @@
+//! Undo and redo of whole player turns.
+//!
+//! The game state is copied before a turn. Once the turn succeeds, only the cells that changed
+//! are kept, along with the non-spatial state (event queue, score, in-progress recording).
+//! Each turn record holds the values from both before and after the turn, so it can be replayed
+//! in either direction.
+
+use std::collections::VecDeque;
+
+use crate::actor::Actor;
+use crate::buildings::Building;
+use crate::datatypes::Coordinate;
+use crate::direction::AbsoluteDirection;
+use crate::engine::tracking_worldlayer::TrackableId;
+use crate::engine::update::UpdatableContainer;
+use crate::error::{Result, Status::ActionFail};
+use crate::eventqueue::EventQueue;
+use crate::game_state::game::Game;
+use crate::game_state::world::{FloorInventory, FloorTile, World};
+use crate::inventory::Item;
+use crate::paradox::Paradox;
+use crate::recording::Recording;
+use crate::score::Score;
+
+const MAX_UNDO_DEPTH: usize = 100;
+
+// Full copy of everything a turn can modify. Only kept for the duration of a turn,
+// to diff against or to roll back a failed turn.
+pub struct GameSnapshot {
+    world: World,
+    event_queue: EventQueue,
+    score: Score,
+    current_recording: Option<Recording>,
+    temp_item: Option<Item>,
+    last_player_facing: AbsoluteDirection,
+}
+
+impl GameSnapshot {
+    pub fn new(game: &Game) -> GameSnapshot {
+        GameSnapshot {
+            world: game.world.clone(),
+            event_queue: game.event_queue.clone(),
+            score: game.score.clone(),
+            current_recording: game.recordings.current_recording.clone(),
+            temp_item: game.recordings.temp_item,
+            last_player_facing: game.recordings.last_player_facing,
+        }
+    }
+
+    pub fn restore(self, game: &mut Game) {
+        game.world = self.world;
+        game.event_queue = self.event_queue;
+        game.score = self.score;
+        game.log.discard_since(game.score.turn);
+        game.recordings.current_recording = self.current_recording;
+        game.recordings.temp_item = self.temp_item;
+        game.recordings.last_player_facing = self.last_player_facing;
+    }
+}
+
+type LayerPatch<T> = Vec<(Coordinate, T)>;
+// Old and new values of the same cells.
+type LayerDiff<T> = (LayerPatch<T>, LayerPatch<T>);
+
+// The parts of a game state that differ between two points in time.
+struct StatePatch {
+    actors: LayerPatch<Option<Actor>>,
+    actor_index: Vec<(TrackableId, Coordinate)>,
+    actor_recycle: Vec<TrackableId>,
+    buildings: LayerPatch<Option<Building>>,
+    items: LayerPatch<FloorInventory>,
+    paradox: LayerPatch<Paradox>,
+    floor: LayerPatch<FloorTile>,
+    event_queue: EventQueue,
+    score: Score,
+    current_recording: Option<Recording>,
+    temp_item: Option<Item>,
+    last_player_facing: AbsoluteDirection,
+}
+
+struct TurnRecord {
+    before: StatePatch,
+    after: StatePatch,
+}
+
+// Returns the old and new values of every cell that differs between two layers.
+fn diff_layer<L>(
+    old: &L,
+    new: &L,
+    dimensions: Coordinate,
+) -> Result<LayerDiff<L::DataType>>
+where
+    L: UpdatableContainer<CoordinateType = Coordinate>,
+    L::DataType: PartialEq,
+{
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    for x in 0..dimensions.x {
+        for y in 0..dimensions.y {
+            let location = Coordinate { x, y };
+            let (old_value, new_value) = (old.get(&location)?, new.get(&location)?);
+            if old_value != new_value {
+                before.push((location, old_value.clone()));
+                after.push((location, new_value.clone()));
+            }
+        }
+    }
+    Ok((before, after))
+}
+
+fn apply_layer<L>(layer: &mut L, patch: &LayerPatch<L::DataType>) -> Result<()>
+where
+    L: UpdatableContainer<CoordinateType = Coordinate>,
+{
+    for (location, value) in patch {
+        layer.mut_set(location, value)?;
+    }
+    Ok(())
+}
+
+impl TurnRecord {
+    fn new(before: &GameSnapshot, after: &Game) -> Result<TurnRecord> {
+        let (old, new) = (&before.world, &after.world);
+        let dimensions = new.dimensions();
+
+        let (actors_before, actors_after) = diff_layer(&old.actors, &new.actors, dimensions)?;
+        let (buildings_before, buildings_after) =
+            diff_layer(&old.buildings, &new.buildings, dimensions)?;
+        let (items_before, items_after) = diff_layer(&old.items, &new.items, dimensions)?;
+        let (paradox_before, paradox_after) = diff_layer(&old.paradox, &new.paradox, dimensions)?;
+        let (floor_before, floor_after) = diff_layer(&old.floor, &new.floor, dimensions)?;
+        let (index_before, recycle_before) = old.actors.get_index_entries();
+        let (index_after, recycle_after) = new.actors.get_index_entries();
+
+        Ok(TurnRecord {
+            before: StatePatch {
+                actors: actors_before,
+                actor_index: index_before,
+                actor_recycle: recycle_before,
+                buildings: buildings_before,
+                items: items_before,
+                paradox: paradox_before,
+                floor: floor_before,
+                event_queue: before.event_queue.clone(),
+                score: before.score.clone(),
+                current_recording: before.current_recording.clone(),
+                temp_item: before.temp_item,
+                last_player_facing: before.last_player_facing,
+            },
+            after: StatePatch {
+                actors: actors_after,
+                actor_index: index_after,
+                actor_recycle: recycle_after,
+                buildings: buildings_after,
+                items: items_after,
+                paradox: paradox_after,
+                floor: floor_after,
+                event_queue: after.event_queue.clone(),
+                score: after.score.clone(),
+                current_recording: after.recordings.current_recording.clone(),
+                temp_item: after.recordings.temp_item,
+                last_player_facing: after.recordings.last_player_facing,
+            },
+        })
+    }
+}
+
+impl StatePatch {
+    fn apply(&self, game: &mut Game) -> Result<()> {
+        let world = &mut game.world;
+        apply_layer(&mut world.actors, &self.actors)?;
+        // Setting cells re-indexes actors as a side effect, so restore the index afterwards.
+        world
+            .actors
+            .mut_restore_index(&self.actor_index, &self.actor_recycle);
+        apply_layer(&mut world.buildings, &self.buildings)?;
+        apply_layer(&mut world.items, &self.items)?;
+        apply_layer(&mut world.paradox, &self.paradox)?;
+        apply_layer(&mut world.floor, &self.floor)?;
+
+        game.event_queue = self.event_queue.clone();
+        game.score = self.score.clone();
+        game.recordings.current_recording = self.current_recording.clone();
+        game.recordings.temp_item = self.temp_item;
+        game.recordings.last_player_facing = self.last_player_facing;
+        Ok(())
+    }
+}
+
+pub struct History {
+    undo_stack: VecDeque<TurnRecord>,
+    redo_stack: Vec<TurnRecord>,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    // Record a completed turn. Taking a new turn discards anything that could be redone.
+    pub fn record(game: &mut Game, before: &GameSnapshot) -> Result<()> {
+        let record = TurnRecord::new(before, game)?;
+        let history = &mut game.history;
+        history.redo_stack.clear();
+        history.undo_stack.push_back(record);
+        if history.undo_stack.len() > MAX_UNDO_DEPTH {
+            history.undo_stack.pop_front();
+        }
+        Ok(())
+    }
+
+    // Turn records only hold the cells each turn changed, so they can't be replayed over changes
+    // made between turns, e.g. starting a recording or pausing a clone. Those forget the history.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    pub fn undo(game: &mut Game) -> Result<()> {
+        let record = game
+            .history
+            .undo_stack
+            .pop_back()
+            .ok_or(ActionFail("nothing to undo"))?;
+        record.before.apply(game)?;
+        game.log.discard_since(game.score.turn);
+        game.history.redo_stack.push(record);
+        Ok(())
+    }
+
+    pub fn redo(game: &mut Game) -> Result<()> {
+        let record = game
+            .history
+            .redo_stack
+            .pop()
+            .ok_or(ActionFail("nothing to redo"))?;
+        record.after.apply(game)?;
+        game.history.undo_stack.push_back(record);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::{Action, SubAction};
+    use crate::devtools;
+    use crate::direction::Direction;
+    use crate::engine::update::Delta;
+    use crate::recording::interface::RecordingModule;
+    use crate::recording::FallbackPolicy;
+    use crate::static_data::Data;
+    use crate::worldgen;
+
+    use super::*;
+
+    #[test]
+    fn undo_redo() {
+        let data = Data::get_test_config();
+        let mut game = worldgen::generate_game(data);
+        let start = *game.get_player_coords().unwrap();
+
+        game.recordings.current_recording = Some(Recording::blank());
+        for _ in 0..2 {
+            game.player_action_and_turn(Action {
+                direction: Direction::Absolute(AbsoluteDirection::E),
+                action: SubAction::Move,
+            })
+            .unwrap();
+        }
+        let end = *game.get_player_coords().unwrap();
+
+        History::undo(&mut game).unwrap();
+        History::undo(&mut game).unwrap();
+        assert_eq!(*game.get_player_coords().unwrap(), start);
+        assert_eq!(game.score.turn, 0);
+        assert_eq!(game.recordings.current_recording.as_ref().unwrap().len(), 0);
+        assert!(History::undo(&mut game).is_err());
+
+        History::redo(&mut game).unwrap();
+        History::redo(&mut game).unwrap();
+        assert_eq!(*game.get_player_coords().unwrap(), end);
+        assert_eq!(game.score.turn, 2);
+        assert_eq!(game.recordings.current_recording.as_ref().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn changes_between_turns_clear_history() {
+        let data = Data::get_test_config();
+        let mut game = worldgen::generate_game(data);
+        let location = *game.get_player_coords().unwrap();
+        let recorder = Item::new(data.items.get("recorder").unwrap(), 1);
+        devtools::grant_item(recorder, location, &game).unwrap().apply(&mut game).unwrap();
+
+        let wait = Action {
+            direction: Direction::Absolute(AbsoluteDirection::E),
+            action: SubAction::Wait,
+        };
+        game.player_action_and_turn(wait).unwrap();
+        assert!(game.history.can_undo());
+
+        let slot = game
+            .get_player_actor()
+            .unwrap()
+            .inventory
+            .get_items()
+            .iter()
+            .position(|i| i.is_some_and(|i| i.definition.name == "recorder"))
+            .unwrap();
+        RecordingModule::init_record(&mut game, slot).unwrap();
+        assert!(!game.history.can_undo());
+        assert!(History::undo(&mut game).is_err());
+
+        // Undoing would put back the fallback from before the change.
+        game.player_action_and_turn(wait).unwrap();
+        RecordingModule::set_fallback(&mut game, FallbackPolicy::Skip).unwrap();
+        assert!(!game.history.can_undo());
+    }
+}
@@ -43,6 +43,14 @@ impl CraftingMenuEntry {
             ));
         }
 
+        if let Some(station) = &definition.station {
+            let building = data
+                .buildings
+                .get(station)
+                .expect("Non-existent building in crafting recipe.");
+            stringpieces.push(format!("\nRequires: {}", building.text.name));
+        }
+
         CraftingMenuEntry {
             definition,
             text: stringpieces.join(" "),
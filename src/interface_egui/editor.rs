@@ -0,0 +1,387 @@
+//! Step-by-step editing of stored recordings.
+//!
+//! Edits are made to a copy held by the application, and saved as a new recording with its own cloner.
+
+use std::collections::HashMap;
+
+use crate::{
+    action::{Action, Condition, SubAction},
+    app::Application,
+    interface_egui::{library, recording::{fallback_label, FALLBACK_CHOICES}},
+    direction::{Direction, RelativeDirection},
+    game_state::game::Game,
+    inventory::BasicInventory,
+    recording::{db::RecordingId, interface::RecordingModule, FallbackPolicy},
+    static_data::Data,
+};
+
+const DIRECTIONS: [(RelativeDirection, &str); 4] = [
+    (RelativeDirection::F, "Forward"),
+    (RelativeDirection::R, "Right"),
+    (RelativeDirection::B, "Back"),
+    (RelativeDirection::L, "Left"),
+];
+
+const STEP_KINDS: [&str; 13] = [
+    "Move",
+    "Take",
+    "Drop",
+    "Use",
+    "Activate building",
+    "Insert",
+    "Extract",
+    "Craft",
+    "Wait",
+    "Jump",
+    "Jump if",
+    "Repeat",
+    "Play recording",
+];
+
+// Steps before this in STEP_KINDS act in the world. The rest are control steps, which can't
+// stand in for a failed step.
+const ACTION_KINDS: usize = 9;
+
+const CONDITION_KINDS: [&str; 4] = [
+    "Floor item is",
+    "Inventory has",
+    "Tile ahead occupied",
+    "Paradox above",
+];
+
+enum Edit {
+    Insert(usize),
+    Remove(usize),
+    Move(usize, usize),
+}
+
+// Non-empty recordings in the db, labelled for menus.
+pub fn stored_recordings(game: &Game) -> Vec<(RecordingId, String)> {
+    let db = &game.recordings.recordings;
+    db.ids()
+        .zip(db.get_all())
+        .filter(|(_, rec)| rec.len() > 0)
+        .map(|(id, rec)| (id, format!("{} ({} steps)", rec.name, rec.len())))
+        .collect()
+}
+
+// Map entries ordered by key, so menus don't shuffle between frames.
+fn sorted<T>(map: &'static HashMap<String, T>) -> Vec<&'static T> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys.into_iter().map(|k| &map[k]).collect()
+}
+
+fn kind_label(action: &SubAction) -> &'static str {
+    let idx = match action {
+        SubAction::Move => 0,
+        SubAction::Take => 1,
+        SubAction::Drop(_) => 2,
+        SubAction::Use(_) => 3,
+        SubAction::ActivateBuilding => 4,
+        SubAction::Insert(_) => 5,
+        SubAction::Extract => 6,
+        SubAction::Craft(_) => 7,
+        SubAction::Wait => 8,
+        SubAction::Jump(_) => 9,
+        SubAction::JumpIf(_, _) => 10,
+        SubAction::Repeat(_, _) => 11,
+        SubAction::Call(_) => 12,
+    };
+    STEP_KINDS[idx]
+}
+
+// A step of the given kind with default parameters. None if there is nothing to fill them with.
+fn default_step(kind: &str, data: &'static Data, stored: &[(RecordingId, String)]) -> Option<SubAction> {
+    Some(match kind {
+        "Move" => SubAction::Move,
+        "Take" => SubAction::Take,
+        "Drop" => SubAction::Drop(0),
+        "Use" => SubAction::Use(0),
+        "Activate building" => SubAction::ActivateBuilding,
+        "Insert" => SubAction::Insert(0),
+        "Extract" => SubAction::Extract,
+        "Craft" => SubAction::Craft(sorted(&data.recipes).first()?),
+        "Jump" => SubAction::Jump(0),
+        "Jump if" => SubAction::JumpIf(Condition::TileAheadOccupied, 0),
+        "Repeat" => SubAction::Repeat(0, 2),
+        "Play recording" => SubAction::Call(stored.first()?.0),
+        _ => SubAction::Wait,
+    })
+}
+
+fn condition_label(condition: &Condition) -> &'static str {
+    match condition {
+        Condition::FloorItemIs(_) => CONDITION_KINDS[0],
+        Condition::InventoryHas(_) => CONDITION_KINDS[1],
+        Condition::TileAheadOccupied => CONDITION_KINDS[2],
+        Condition::ParadoxAbove(_) => CONDITION_KINDS[3],
+    }
+}
+
+fn default_condition(kind: &str, data: &'static Data) -> Option<Condition> {
+    Some(match kind {
+        "Floor item is" => Condition::FloorItemIs(sorted(&data.items).first()?),
+        "Inventory has" => Condition::InventoryHas(sorted(&data.items).first()?),
+        "Paradox above" => Condition::ParadoxAbove(0.0),
+        _ => Condition::TileAheadOccupied,
+    })
+}
+
+fn direction_editor(ui: &mut egui::Ui, direction: &mut Direction) {
+    let text = match direction {
+        Direction::Relative(rel) => DIRECTIONS
+            .iter()
+            .find(|(d, _)| d == rel)
+            .map_or("", |(_, label)| label)
+            .to_string(),
+        Direction::Absolute(abs) => format!("{:?}", abs),
+    };
+    egui::ComboBox::from_id_salt("direction")
+        .selected_text(text)
+        .show_ui(ui, |ui| {
+            for (rel, label) in DIRECTIONS {
+                ui.selectable_value(direction, Direction::Relative(rel), label);
+            }
+        });
+}
+
+// Step numbers are shown from 1, as in the recording window. Targets one past the end finish the recording.
+fn step_number(ui: &mut egui::Ui, idx: &mut usize, max: usize, prefix: &str) {
+    let mut number = *idx + 1;
+    ui.add(egui::DragValue::new(&mut number).range(1..=max + 1).prefix(prefix));
+    *idx = number - 1;
+}
+
+fn condition_editor(ui: &mut egui::Ui, condition: &mut Condition, data: &'static Data) {
+    let mut kind = condition_label(condition);
+    egui::ComboBox::from_id_salt("condition")
+        .selected_text(kind)
+        .show_ui(ui, |ui| {
+            for label in CONDITION_KINDS {
+                ui.selectable_value(&mut kind, label, label);
+            }
+        });
+    if kind != condition_label(condition) {
+        if let Some(new) = default_condition(kind, data) {
+            *condition = new;
+        }
+    }
+    match condition {
+        Condition::FloorItemIs(item) | Condition::InventoryHas(item) => {
+            egui::ComboBox::from_id_salt("condition_item")
+                .selected_text(&item.text.name)
+                .show_ui(ui, |ui| {
+                    for def in sorted(&data.items) {
+                        ui.selectable_value(item, def, &def.text.name);
+                    }
+                });
+        }
+        Condition::ParadoxAbove(threshold) => {
+            ui.add(egui::DragValue::new(threshold).speed(1.0));
+        }
+        Condition::TileAheadOccupied => {}
+    }
+}
+
+fn step_editor(
+    ui: &mut egui::Ui,
+    step: &mut Action,
+    idx: usize,
+    len: usize,
+    data: &'static Data,
+    stored: &[(RecordingId, String)],
+    kinds: &[&'static str],
+) {
+    direction_editor(ui, &mut step.direction);
+
+    let mut kind = kind_label(&step.action);
+    egui::ComboBox::from_id_salt("kind")
+        .selected_text(kind)
+        .show_ui(ui, |ui| {
+            for &label in kinds {
+                ui.selectable_value(&mut kind, label, label);
+            }
+        });
+    if kind != kind_label(&step.action) {
+        if let Some(action) = default_step(kind, data, stored) {
+            step.action = action;
+        }
+    }
+
+    let slots = BasicInventory::default().get_items().len();
+    match &mut step.action {
+        SubAction::Drop(slot) | SubAction::Use(slot) | SubAction::Insert(slot) => {
+            step_number(ui, slot, slots - 1, "Slot ");
+        }
+        SubAction::Craft(recipe) => {
+            egui::ComboBox::from_id_salt("recipe")
+                .selected_text(&recipe.name)
+                .show_ui(ui, |ui| {
+                    for def in sorted(&data.recipes) {
+                        ui.selectable_value(recipe, def, &def.name);
+                    }
+                });
+        }
+        SubAction::Jump(target) => step_number(ui, target, len, "to step "),
+        SubAction::JumpIf(condition, target) => {
+            condition_editor(ui, condition, data);
+            step_number(ui, target, len, "to step ");
+        }
+        SubAction::Repeat(start, count) => {
+            step_number(ui, start, idx.saturating_sub(1), "from step ");
+            ui.add(egui::DragValue::new(count).range(1..=99).prefix("x"));
+        }
+        SubAction::Call(callee) => {
+            let text = stored
+                .iter()
+                .find(|(id, _)| id == callee)
+                .map_or("", |(_, label)| label.as_str());
+            egui::ComboBox::from_id_salt("callee")
+                .selected_text(text)
+                .show_ui(ui, |ui| {
+                    for (id, label) in stored {
+                        ui.selectable_value(callee, *id, label);
+                    }
+                });
+        }
+        _ => {}
+    }
+}
+
+// What to do when a step fails. Picking another action starts it off as waiting in place.
+fn fallback_editor(ui: &mut egui::Ui, policy: &mut FallbackPolicy, idx: usize, len: usize, data: &'static Data) {
+    let alternate = match policy {
+        FallbackPolicy::Alternate(_) => *policy,
+        _ => FallbackPolicy::Alternate(Action {
+            direction: Direction::Relative(RelativeDirection::F),
+            action: SubAction::Wait,
+        }),
+    };
+    egui::ComboBox::from_id_salt("fallback")
+        .selected_text(fallback_label(policy))
+        .show_ui(ui, |ui| {
+            for (choice, label) in FALLBACK_CHOICES {
+                ui.selectable_value(policy, choice, label);
+            }
+            ui.selectable_value(policy, alternate, fallback_label(&alternate));
+        });
+    if let FallbackPolicy::Alternate(action) = policy {
+        ui.push_id("alternate", |ui| {
+            step_editor(ui, action, idx, len, data, &[], &STEP_KINDS[..ACTION_KINDS]);
+        });
+    }
+}
+
+// A step's own fallback, overriding the recording's.
+fn step_fallback_editor(
+    ui: &mut egui::Ui,
+    fallbacks: &mut HashMap<usize, FallbackPolicy>,
+    default: FallbackPolicy,
+    idx: usize,
+    len: usize,
+    data: &'static Data,
+) {
+    let mut overridden = fallbacks.contains_key(&idx);
+    if ui.checkbox(&mut overridden, "Own fallback").changed() {
+        if overridden {
+            fallbacks.insert(idx, default);
+        } else {
+            fallbacks.remove(&idx);
+        }
+    }
+    if let Some(policy) = fallbacks.get_mut(&idx) {
+        fallback_editor(ui, policy, idx, len, data);
+    }
+}
+
+pub fn show(app: &mut Application, ctx: &egui::Context) {
+    let Some(mut draft) = app.editor.take() else {
+        return;
+    };
+    let data = app.data;
+    let stored = stored_recordings(&app.game.borrow());
+    let mut open = true;
+    let mut edit = None;
+    let mut save = false;
+
+    egui::Window::new("Recording editor")
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Name: ");
+                ui.text_edit_singleline(&mut draft.name);
+            });
+            ui.label("Note:");
+            ui.text_edit_multiline(&mut draft.note);
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut draft.should_loop, "Loop");
+                if !draft.should_loop {
+                    ui.add(
+                        egui::DragValue::new(&mut draft.repeat_count)
+                            .range(1..=99)
+                            .prefix("Times to play: "),
+                    );
+                }
+            });
+            let len = draft.len();
+            ui.horizontal(|ui| {
+                ui.label("When a step fails: ");
+                fallback_editor(ui, &mut draft.fallback, 0, len, data);
+            });
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                for idx in 0..len {
+                    ui.push_id(idx, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}.", idx + 1));
+                            step_editor(ui, &mut draft.command_list[idx], idx, len, data, &stored, &STEP_KINDS);
+                            if ui.small_button("⏶").clicked() && idx > 0 {
+                                edit = Some(Edit::Move(idx, idx - 1));
+                            }
+                            if ui.small_button("⏷").clicked() && idx + 1 < len {
+                                edit = Some(Edit::Move(idx, idx + 1));
+                            }
+                            if ui.small_button("+").on_hover_text("Add a step after this one").clicked() {
+                                edit = Some(Edit::Insert(idx + 1));
+                            }
+                            if ui.small_button("🗙").clicked() {
+                                edit = Some(Edit::Remove(idx));
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            let default = draft.fallback;
+                            step_fallback_editor(ui, &mut draft.step_fallbacks, default, idx, len, data);
+                        });
+                    });
+                }
+            });
+            ui.horizontal(|ui| {
+                if len == 0 && ui.button("Add step").clicked() {
+                    edit = Some(Edit::Insert(0));
+                }
+                if ui.button("Save as new cloner").on_hover_text(library::duplicate_cost(data)).clicked() {
+                    save = true;
+                }
+            });
+        });
+
+    let wait = Action {
+        direction: Direction::Relative(RelativeDirection::F),
+        action: SubAction::Wait,
+    };
+    match edit {
+        Some(Edit::Insert(idx)) => draft.insert_step(idx, wait),
+        Some(Edit::Remove(idx)) => draft.remove_step(idx),
+        Some(Edit::Move(from, to)) => draft.move_step(from, to),
+        None => {}
+    }
+    if save {
+        let recording = draft.clone();
+        app.queue_act(Box::new(move |game: &mut Game| {
+            RecordingModule::save_new(game, recording.clone())
+        }));
+    }
+    if open {
+        app.editor = Some(draft);
+    }
+}
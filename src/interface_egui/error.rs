@@ -12,6 +12,7 @@ pub fn show(app: &mut Application, ctx: &Context) {
                     "Some operation returned out of bounds. This should not be player-visible."
                 }
                 Status::StateUpdateError => "Error updating world state.",
+                Status::UpdateConflict(str) => str,
                 Status::Error(str) => str,
             };
             let button = ui.button(text);
@@ -1,6 +1,6 @@
 use egui::Ui;
 
-use crate::{app::Application, game_state::world::WorldCell, score::Score, static_data::ObjectDescriptor};
+use crate::{app::Application, game_state::world::WorldCell, inventory::Item, score::Score, static_data::ObjectDescriptor};
 
 fn object_info(ui: &mut Ui, object: &'static ObjectDescriptor) {
     ui.vertical(|ui| {
@@ -9,6 +9,17 @@ fn object_info(ui: &mut Ui, object: &'static ObjectDescriptor) {
     });
 }
 
+fn slot_info(ui: &mut Ui, label: &str, items: &[Option<Item>]) {
+    if items.is_empty() {
+        return;
+    }
+    let contents: Vec<&str> = items
+        .iter()
+        .map(|i| i.as_ref().map_or("-", |i| i.definition.text.name.as_str()))
+        .collect();
+    ui.label(format!("{}: {}", label, contents.join(", ")));
+}
+
 fn cell_info(ui: &mut Ui, cell: &WorldCell) {
     ui.label(format!("Local Paradox level: {}", cell.paradox.0));
     if let Some(a) = cell.actor {
@@ -17,6 +28,8 @@ fn cell_info(ui: &mut Ui, cell: &WorldCell) {
 
     if let Some(b) = cell.building {
         object_info(ui, b.definition);
+        slot_info(ui, "Input", b.input.get_items());
+        slot_info(ui, "Output", b.output.get_items());
     }
     if let Some(i) = cell.items[0] {
         object_info(ui, i.definition);
@@ -1,4 +1,5 @@
 use crate::error::Result;
+use crate::eventqueue::Deployment;
 use crate::inventory::Item;
 use crate::recording::interface::RecordingModule;
 use crate::app::GameFn;
@@ -10,7 +11,7 @@ use crate::{
 };
 
 fn use_dispatch(idx: usize, item: Item) -> Box<GameFn> {
-    if item.definition.name == "recorder" {
+    if item.definition.recorder.is_some() {
         return Box::new(move |game: &mut Game| RecordingModule::init_record(game, idx));
     } else {
         return Box::new(move |game: &mut Game| {
@@ -22,6 +23,36 @@ fn use_dispatch(idx: usize, item: Item) -> Box<GameFn> {
     }
 }
 
+// When a cloner's clone starts, and how often it plays again. Changes are stored on the cloner.
+fn deployment_editor(app: &mut Application, ui: &mut egui::Ui, idx: usize, current: Deployment) {
+    let mut deployment = current;
+    ui.add(
+        egui::DragValue::new(&mut deployment.delay)
+            .range(0..=99)
+            .prefix("Start in ")
+            .suffix(" turns"),
+    );
+    let mut repeats = deployment.interval.is_some();
+    ui.checkbox(&mut repeats, "Play again")
+        .on_hover_text("Instead of vanishing, the clone plays its recording again after a pause");
+    deployment.interval = None;
+    if repeats {
+        let mut interval = current.interval.unwrap_or(1);
+        ui.add(
+            egui::DragValue::new(&mut interval)
+                .range(1..=99)
+                .prefix("after ")
+                .suffix(" turns"),
+        );
+        deployment.interval = Some(interval);
+    }
+    if deployment != current {
+        app.queue_act(Box::new(move |game: &mut Game| {
+            RecordingModule::set_deployment(game, idx, deployment)
+        }));
+    }
+}
+
 pub fn inventory(app: &mut Application, ctx: &egui::Context) -> Result<()> {
     let inventory = app.game.borrow().get_player_actor()?.inventory;
 
@@ -33,7 +64,22 @@ pub fn inventory(app: &mut Application, ctx: &egui::Context) -> Result<()> {
                     ui.label(format!("{}", i + 1));
                     if let Some(item) = items[i] {
                         let name = &item.definition.text.name;
-                        ui.label(name);
+                        let label = ui.label(name);
+                        // Cloners show which recording they play.
+                        if let Some(id) = item.recording {
+                            let game = app.game.borrow();
+                            let recording = game.recordings.get(id);
+                            ui.label(&recording.name);
+                            let mut tooltip = format!("{} steps", recording.len());
+                            if !recording.note.is_empty() {
+                                tooltip = format!("{}\n{}", tooltip, recording.note);
+                            }
+                            label.on_hover_text(tooltip);
+                            drop(game);
+                            deployment_editor(app, ui, i, item.deployment);
+                        } else {
+                            label.on_hover_text(&item.definition.text.description);
+                        }
                         let button = ui.button("Use");
                         if button.clicked() {
                             app.queue_act(use_dispatch(i, item));
@@ -47,6 +93,15 @@ pub fn inventory(app: &mut Application, ctx: &egui::Context) -> Result<()> {
                                 })
                             }));
                         }
+                        let button = ui.button("Insert");
+                        if button.clicked() {
+                            app.queue_act(Box::new(move |game: &mut Game| {
+                                game.player_action_and_turn(Action {
+                                    direction: Direction::Relative(RelativeDirection::F),
+                                    action: action::SubAction::Insert(i),
+                                })
+                            }));
+                        }
                     }
                 });
             }
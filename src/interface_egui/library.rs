@@ -0,0 +1,137 @@
+//! List of stored recordings, to edit or make more cloners from.
+
+use crate::{
+    app::Application,
+    error::Status::ActionFail,
+    game_state::game::Game,
+    recording::{interface::RecordingModule, text, Recording},
+    static_data::Data,
+};
+
+fn inventory_text(recording: &Recording) -> String {
+    let names: Vec<&str> = recording
+        .inventory
+        .get_items()
+        .iter()
+        .flatten()
+        .map(|item| item.definition.text.name.as_str())
+        .collect();
+    if names.is_empty() {
+        "Starts with nothing".to_string()
+    } else {
+        format!("Starts with: {}", names.join(", "))
+    }
+}
+
+// Price of a cloner made from a stored recording.
+pub fn duplicate_cost(data: &Data) -> String {
+    let Some(recipe) = data.recipes.get("recorder") else {
+        return String::new();
+    };
+    let ingredients: Vec<String> = recipe
+        .ingredients
+        .iter()
+        .zip(&recipe.ingredient_counts)
+        .map(|(name, count)| {
+            let name = data.items.get(name).map_or(name, |def| &def.text.name);
+            format!("{} x {}", name, count)
+        })
+        .collect();
+    format!("Costs {}", ingredients.join(", "))
+}
+
+pub fn show(app: &mut Application, ctx: &egui::Context) {
+    let cost = duplicate_cost(app.data);
+    egui::Window::new("Recordings").default_open(false).show(ctx, |ui| {
+        let game = app.game.clone();
+        let game = game.borrow();
+        let db = &game.recordings.recordings;
+        let player = game.get_player_actor().ok().map(|a| a.actor_id);
+        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            for (id, recording) in db.ids().zip(db.get_all()) {
+                if recording.len() == 0 {
+                    continue;
+                }
+                let plays = if recording.should_loop {
+                    "loops".to_string()
+                } else {
+                    format!("plays {}x", recording.repeat_count)
+                };
+                ui.group(|ui| {
+                    let creator = match recording.creator {
+                        Some(id) if Some(id) == player => "you".to_string(),
+                        Some(id) => format!("actor {}", id.0),
+                        None => "nobody".to_string(),
+                    };
+                    ui.label(format!(
+                        "{}: {} steps, {}, made by {} on turn {}",
+                        recording.name,
+                        recording.len(),
+                        plays,
+                        creator,
+                        recording.created_turn
+                    ));
+                    ui.label(inventory_text(recording));
+                    if !recording.note.is_empty() {
+                        ui.label(&recording.note);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Edit").clicked() {
+                            app.editor = Some(recording.clone());
+                        }
+                        if ui.button("Duplicate").on_hover_text(&cost).clicked() {
+                            app.queue_act(Box::new(move |game: &mut Game| {
+                                RecordingModule::duplicate(game, id)
+                            }));
+                        }
+                        if ui.button("Export").on_hover_text("Copy as text").clicked() {
+                            app.recording_text = text::to_text(recording, db, app.data);
+                            app.recording_text_error = None;
+                            ui.ctx().copy_text(app.recording_text.clone());
+                        }
+                        let previewing = app.preview.as_ref().is_some_and(|p| p.recording == id);
+                        if !previewing && ui.button("Preview").on_hover_text("Show where a clone would go if deployed now").clicked() {
+                            app.show_preview(id);
+                        } else if previewing && ui.button("Hide preview").clicked() {
+                            app.preview = None;
+                        }
+                    });
+                    match app.preview.as_ref().filter(|p| p.recording == id).map(|p| &p.steps) {
+                        Some(Err(ActionFail(reason))) => {
+                            ui.label(format!("Can't preview: {}", reason));
+                        }
+                        Some(Err(err)) => {
+                            ui.label(format!("Can't preview: {:?}", err));
+                        }
+                        _ => {}
+                    }
+                });
+            }
+        });
+        drop(game);
+
+        ui.collapsing("Import / export", |ui| {
+            ui.add(
+                egui::TextEdit::multiline(&mut app.recording_text)
+                    .code_editor()
+                    .desired_rows(8),
+            );
+            if let Some(err) = &app.recording_text_error {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+            let import_cost = format!("Needs the recorder it names and its starting items. {}", cost);
+            if ui.button("Import as new cloner").on_hover_text(import_cost).clicked() {
+                let parsed = text::from_text(&app.recording_text, &app.game.borrow().recordings.recordings, app.data);
+                match parsed {
+                    Ok(recording) => {
+                        app.recording_text_error = None;
+                        app.queue_act(Box::new(move |game: &mut Game| {
+                            RecordingModule::import(game, recording.clone())
+                        }));
+                    }
+                    Err(err) => app.recording_text_error = Some(err.to_string()),
+                }
+            }
+        });
+    });
+}
@@ -0,0 +1,50 @@
+//! The turn log, filtered by kind of event and by text.
+
+use crate::{app::Application, eventlog::LogCategory};
+
+#[derive(Default)]
+pub struct LogFilter {
+    pub hidden: Vec<LogCategory>,
+    // Only entries containing this text are shown, e.g. "actor 3".
+    pub text: String,
+}
+
+pub fn show(app: &mut Application, ctx: &egui::Context) {
+    egui::Window::new("Log").default_open(false).show(ctx, |ui| {
+        let filter = &mut app.log_filter;
+        ui.horizontal(|ui| {
+            for category in LogCategory::ALL {
+                let mut shown = !filter.hidden.contains(&category);
+                if ui.checkbox(&mut shown, category.label()).changed() {
+                    if shown {
+                        filter.hidden.retain(|c| *c != category);
+                    } else {
+                        filter.hidden.push(category);
+                    }
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Filter: ");
+            ui.text_edit_singleline(&mut filter.text);
+        });
+
+        let game = app.game.borrow();
+        let lines: Vec<String> = game
+            .log
+            .entries()
+            .filter(|e| !filter.hidden.contains(&e.event.category()))
+            .map(|e| e.to_string())
+            .filter(|line| line.contains(filter.text.as_str()))
+            .collect();
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, lines.len(), |ui, rows| {
+                for line in &lines[rows] {
+                    ui.label(line);
+                }
+            });
+    });
+}
@@ -2,6 +2,11 @@ pub mod worldwindow;
 pub mod movement;
 pub mod crafting;
 pub mod recording;
+pub mod editor;
+pub mod library;
+pub mod log;
+pub mod roster;
 pub mod inventory;
 pub mod error;
-pub mod info;
\ No newline at end of file
+pub mod info;
+pub mod save;
\ No newline at end of file
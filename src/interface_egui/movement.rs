@@ -2,14 +2,17 @@ use crate::{
     action::{self, Action},
     direction::{self, AbsoluteDirection},
     game_state::game::Game,
+    history::History,
     app::Application,
 };
 
 pub fn movement(app: &mut Application, ctx: &egui::Context) {
+    // Keys typed into a text field, e.g. a recording's name, aren't hotkeys.
+    let hotkeys = !ctx.wants_keyboard_input();
     let window = egui::SidePanel::right("Controls").show(ctx, |ui| {
         let button = ui.button("West (A)");
         if button.clicked()
-            || ui.input(|i| i.key_pressed(egui::Key::ArrowLeft) || i.key_pressed(egui::Key::A))
+            || (hotkeys && ui.input(|i| i.key_pressed(egui::Key::ArrowLeft) || i.key_pressed(egui::Key::A)))
         {
             app.queue_act(Box::new(|game: &mut Game| {
                 game.player_action_and_turn(Action {
@@ -20,7 +23,7 @@ pub fn movement(app: &mut Application, ctx: &egui::Context) {
         }
         let button = ui.button("North (W)");
         if button.clicked()
-            || ui.input(|i| i.key_pressed(egui::Key::ArrowUp) || i.key_pressed(egui::Key::W))
+            || (hotkeys && ui.input(|i| i.key_pressed(egui::Key::ArrowUp) || i.key_pressed(egui::Key::W)))
         {
             app.queue_act(Box::new(|game: &mut Game| {
                 game.player_action_and_turn(Action {
@@ -32,7 +35,7 @@ pub fn movement(app: &mut Application, ctx: &egui::Context) {
 
         let button = ui.button("East (D)");
         if button.clicked()
-            || ui.input(|i| i.key_pressed(egui::Key::ArrowRight) || i.key_pressed(egui::Key::D))
+            || (hotkeys && ui.input(|i| i.key_pressed(egui::Key::ArrowRight) || i.key_pressed(egui::Key::D)))
         {
             app.queue_act(Box::new(|game: &mut Game| {
                 game.player_action_and_turn(Action {
@@ -44,7 +47,7 @@ pub fn movement(app: &mut Application, ctx: &egui::Context) {
 
         let button = ui.button("South (S)");
         if button.clicked()
-            || ui.input(|i| i.key_pressed(egui::Key::ArrowDown) || i.key_pressed(egui::Key::S))
+            || (hotkeys && ui.input(|i| i.key_pressed(egui::Key::ArrowDown) || i.key_pressed(egui::Key::S)))
         {
             app.queue_act(Box::new(|game: &mut Game| {
                 game.player_action_and_turn(Action {
@@ -55,7 +58,7 @@ pub fn movement(app: &mut Application, ctx: &egui::Context) {
         }
 
         let button = ui.button("Wait (Space0");
-        if button.clicked() || ui.input(|i| i.key_pressed(egui::Key::Space)) {
+        if button.clicked() || (hotkeys && ui.input(|i| i.key_pressed(egui::Key::Space))) {
             app.queue_act(Box::new(|game: &mut Game| {
                 game.player_action_and_turn(Action {
                     direction: direction::Direction::Absolute(AbsoluteDirection::S),
@@ -65,7 +68,7 @@ pub fn movement(app: &mut Application, ctx: &egui::Context) {
         }
 
         let button = ui.button("Take (T)");
-        if button.clicked() ||  ui.input(|i| i.key_pressed(egui::Key::T)) {
+        if button.clicked() || (hotkeys && ui.input(|i| i.key_pressed(egui::Key::T))) {
             app.queue_act(Box::new(|game: &mut Game| {
                 game.player_action_and_turn(Action {
                     direction: direction::Direction::Relative(direction::RelativeDirection::F),
@@ -74,7 +77,7 @@ pub fn movement(app: &mut Application, ctx: &egui::Context) {
             }));
         }
         let button = ui.button("Use Building/Mine (U)");
-        if button.clicked()  || ui.input(|i| i.key_pressed(egui::Key::U)) {
+        if button.clicked()  || (hotkeys && ui.input(|i| i.key_pressed(egui::Key::U))) {
             app.queue_act(Box::new(|game: &mut Game| {
                 game.player_action_and_turn(Action {
                     direction: direction::Direction::Relative(direction::RelativeDirection::F),
@@ -82,5 +85,28 @@ pub fn movement(app: &mut Application, ctx: &egui::Context) {
                 })
             }));
         }
+        let button = ui.button("Extract from building ahead (E)");
+        if button.clicked()  || (hotkeys && ui.input(|i| i.key_pressed(egui::Key::E))) {
+            app.queue_act(Box::new(|game: &mut Game| {
+                game.player_action_and_turn(Action {
+                    direction: direction::Direction::Relative(direction::RelativeDirection::F),
+                    action: action::SubAction::Extract,
+                })
+            }));
+        }
+
+        ui.separator();
+        let (can_undo, can_redo) = {
+            let history = &app.game.borrow().history;
+            (history.can_undo(), history.can_redo())
+        };
+        let button = ui.add_enabled(can_undo, egui::Button::new("Undo turn (Z)"));
+        if button.clicked() || (can_undo && hotkeys && ui.input(|i| i.key_pressed(egui::Key::Z))) {
+            app.queue_act(Box::new(History::undo));
+        }
+        let button = ui.add_enabled(can_redo, egui::Button::new("Redo turn (Y)"));
+        if button.clicked() || (can_redo && hotkeys && ui.input(|i| i.key_pressed(egui::Key::Y))) {
+            app.queue_act(Box::new(History::redo));
+        }
     });
 }
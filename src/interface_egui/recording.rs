@@ -1,31 +1,176 @@
-use crate::{game_state::game::Game, recording::{interface::RecordingModule}, app::Application};
+use crate::{game_state::game::Game, recording::{db::RecordingId, interface::RecordingModule, FallbackPolicy}, static_data::RecorderDefinition, app::Application};
+
+use super::editor;
 
 pub struct RecorderMenu {}
 
+// Policies that can be picked without choosing an action. Alternate actions are picked in the editor.
+pub const FALLBACK_CHOICES: [(FallbackPolicy, &str); 5] = [
+    (FallbackPolicy::Paradox, "Fail (generates paradox)"),
+    (FallbackPolicy::Retry, "Retry a few times"),
+    (FallbackPolicy::Wait, "Wait until it succeeds"),
+    (FallbackPolicy::Skip, "Skip the step"),
+    (FallbackPolicy::Despawn, "Despawn"),
+];
+
+// A number kept in egui's memory between frames, as the menu itself is stateless.
+fn remembered_number(ui: &mut egui::Ui, key: &str, default: u32, range: std::ops::RangeInclusive<u32>, prefix: &str) -> u32 {
+    let id = egui::Id::new("recorder_menu").with(key);
+    let mut value = ui.data(|d| d.get_temp::<u32>(id)).unwrap_or(default);
+    ui.add(egui::DragValue::new(&mut value).range(range).prefix(prefix));
+    ui.data_mut(|d| d.insert_temp(id, value));
+    value
+}
+
+// A choice of stored recording, kept in egui's memory like remembered_number. choices must not be empty.
+fn remembered_recording(ui: &mut egui::Ui, key: &str, choices: &[(RecordingId, String)]) -> RecordingId {
+    let id = egui::Id::new("recorder_menu").with(key);
+    let mut value = ui
+        .data(|d| d.get_temp::<RecordingId>(id))
+        .filter(|c| choices.iter().any(|(r, _)| r == c))
+        .unwrap_or(choices[0].0);
+    let selected_text = choices
+        .iter()
+        .find(|(r, _)| *r == value)
+        .map_or("", |(_, label)| label.as_str());
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            for (r, label) in choices {
+                ui.selectable_value(&mut value, *r, label);
+            }
+        });
+    ui.data_mut(|d| d.insert_temp(id, value));
+    value
+}
+
+fn recorder_abilities(limits: &RecorderDefinition) -> String {
+    let abilities = [
+        (limits.allow_loop, "loops"),
+        (limits.allow_conditionals, "conditional steps"),
+        (limits.record_failures, "failed actions"),
+    ];
+    let names: Vec<&str> = abilities.iter().filter(|(a, _)| *a).map(|(_, name)| *name).collect();
+    if names.is_empty() {
+        "This recorder has no special abilities.".to_string()
+    } else {
+        format!("This recorder can hold: {}", names.join(", "))
+    }
+}
+
+pub fn fallback_label(policy: &FallbackPolicy) -> &'static str {
+    match policy {
+        FallbackPolicy::Alternate(_) => "Perform another action",
+        _ => FALLBACK_CHOICES
+            .iter()
+            .find(|(p, _)| p == policy)
+            .map_or("", |(_, label)| label),
+    }
+}
+
 impl RecorderMenu {
     pub fn show(app: &mut Application, ctx: &egui::Context) {
         let window = egui::Window::new("Recording").show(ctx, |ui| {
             let mut now_recording = false;
             let mut has_recording = false;
+            let mut fallback = FallbackPolicy::Paradox;
+            let mut steps = 0;
+            let mut can_loop = true;
+            let mut can_record_failures = true;
+            let mut record_failures = false;
+            let callable = editor::stored_recordings(&app.game.borrow());
             {
                 let recoding_module = &app.game.borrow().recordings;  
                 if let Some(rec) = &recoding_module.current_recording {
-                    ui.label(format!("Now recording {} steps", rec.len()));
+                    match rec.limits().and_then(|l| l.max_steps) {
+                        Some(max) => ui.label(format!("Now recording {}/{} steps", rec.len(), max)),
+                        None => ui.label(format!("Now recording {} steps", rec.len())),
+                    };
+                    if let Some(limits) = rec.limits() {
+                        ui.label(recorder_abilities(limits));
+                        can_loop = limits.allow_loop;
+                        can_record_failures = limits.record_failures;
+                    }
                     now_recording = true;
+                    fallback = rec.fallback;
+                    record_failures = rec.record_failures;
+                    steps = rec.len();
                 }
                 has_recording = recoding_module.temp_item.is_some()
             }
             if now_recording {
-            if ui.button("End current recording; loop.").clicked() {
-                app.queue_act(Box::new(|game: &mut Game| {
-                    RecordingModule::end_record(game, true)
+            let mut selected = fallback;
+            egui::ComboBox::from_label("When a step fails")
+                .selected_text(fallback_label(&fallback))
+                .show_ui(ui, |ui| {
+                    for (policy, label) in FALLBACK_CHOICES {
+                        ui.selectable_value(&mut selected, policy, label);
+                    }
+                });
+            if selected != fallback {
+                app.queue_act(Box::new(move |game: &mut Game| {
+                    RecordingModule::set_fallback(game, selected)
                 }));
             }
-            if ui.button("End current recording; No loop.").clicked() {
-                app.queue_act(Box::new(|game: &mut Game| {
-                    RecordingModule::end_record(game, false)
+            if can_record_failures {
+                let mut selected = record_failures;
+                ui.checkbox(&mut selected, "Record failed actions");
+                if selected != record_failures {
+                    app.queue_act(Box::new(move |game: &mut Game| {
+                        RecordingModule::set_record_failures(game, selected)
+                    }));
+                }
+            }
+            if steps > 0 {
+                ui.horizontal(|ui| {
+                    let last = steps as u32;
+                    let start = remembered_number(ui, "block_start", 1, 1..=last, "From step ");
+                    let count = remembered_number(ui, "block_count", 2, 1..=99, "x");
+                    if ui.button(format!("Repeat steps {}-{}", start, last)).clicked() {
+                        app.queue_act(Box::new(move |game: &mut Game| {
+                            RecordingModule::add_repeat(game, start as usize - 1, count)
+                        }));
+                    }
+                });
+            }
+            if !callable.is_empty() {
+                ui.horizontal(|ui| {
+                    let callee = remembered_recording(ui, "callee", &callable);
+                    if ui.button("Play recording").clicked() {
+                        app.queue_act(Box::new(move |game: &mut Game| {
+                            RecordingModule::add_call(game, callee)
+                        }));
+                    }
+                });
+            }
+            let name_id = egui::Id::new("recorder_menu").with("name");
+            let mut name = ui.data(|d| d.get_temp::<String>(name_id)).unwrap_or_default();
+            ui.horizontal(|ui| {
+                ui.label("Name: ");
+                ui.text_edit_singleline(&mut name);
+            });
+            let mut ended = false;
+            if can_loop && ui.button("End current recording; loop.").clicked() {
+                let name = name.clone();
+                app.queue_act(Box::new(move |game: &mut Game| {
+                    RecordingModule::end_record(game, true, 1, &name)
                 }));
+                ended = true;
+            }
+            ui.horizontal(|ui| {
+                let plays = remembered_number(ui, "plays", 1, 1..=99, "Times to play: ");
+                if ui.button("End current recording; No loop.").clicked() {
+                    let name = name.clone();
+                    app.queue_act(Box::new(move |game: &mut Game| {
+                        RecordingModule::end_record(game, false, plays, &name)
+                    }));
+                    ended = true;
+                }
+            });
+            if ended {
+                name.clear();
             }
+            ui.data_mut(|d| d.insert_temp(name_id, name));
         }
         if has_recording {
             if ui.button("Add recording to inventory").clicked() {
@@ -0,0 +1,101 @@
+//! Every running npc, with controls for the player's clones.
+
+use crate::{
+    app::Application,
+    eventqueue::StepResult,
+    game_state::game::Game,
+    roster::{self, RosterEntry},
+};
+
+fn status_text(entry: &RosterEntry, turn: i64) -> String {
+    if entry.paused {
+        return "Paused".to_string();
+    }
+    match entry.turn - turn {
+        ..=0 => "Acts this turn".to_string(),
+        1 => "Acts next turn".to_string(),
+        n => format!("Acts in {} turns", n),
+    }
+}
+
+fn last_result_text(result: &Option<StepResult>) -> String {
+    match result {
+        None => "Hasn't acted yet".to_string(),
+        Some(StepResult::Done) => "Last step done".to_string(),
+        Some(StepResult::InProgress) => "Partway through a step".to_string(),
+        Some(StepResult::Failed(reason)) => format!("Last step failed: {}", reason),
+    }
+}
+
+fn inventory_text(entry: &RosterEntry) -> String {
+    let names: Vec<&str> = entry
+        .inventory
+        .get_items()
+        .iter()
+        .flatten()
+        .map(|item| item.definition.text.name.as_str())
+        .collect();
+    if names.is_empty() {
+        "Carrying nothing".to_string()
+    } else {
+        format!("Carrying: {}", names.join(", "))
+    }
+}
+
+pub fn show(app: &mut Application, ctx: &egui::Context) {
+    egui::Window::new("Clones").default_open(false).show(ctx, |ui| {
+        let game = app.game.clone();
+        let game = game.borrow();
+        let entries = roster::roster(&game);
+        if entries.is_empty() {
+            ui.label("Nothing is running");
+        }
+        if app.camera.is_some() && ui.button("Back to player").clicked() {
+            app.camera = None;
+        }
+        egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            for entry in entries {
+                let actor = entry.actor;
+                let recording = game.recordings.get(entry.recording);
+                ui.group(|ui| {
+                    ui.label(format!(
+                        "{} {}: {}",
+                        entry.descriptor.text.name,
+                        actor.0,
+                        status_text(&entry, game.event_queue.turn)
+                    ));
+                    ui.label(format!(
+                        "{} ({}), step {} of {}",
+                        recording.name,
+                        entry.recording,
+                        entry.step + 1,
+                        recording.len()
+                    ));
+                    ui.label(format!("At {}, {}", entry.location.x, entry.location.y));
+                    ui.label(inventory_text(&entry));
+                    if let Some(left) = entry.paradox_left {
+                        ui.label(format!("Paradox tolerance left: {:.0}", left));
+                    }
+                    ui.label(last_result_text(&entry.last_result));
+                    ui.horizontal(|ui| {
+                        if ui.button("Show").on_hover_text("Centre the view on it").clicked() {
+                            app.camera = Some(actor);
+                        }
+                        if !entry.controllable() {
+                            return;
+                        }
+                        if !entry.paused && ui.button("Pause").clicked() {
+                            app.queue_act(Box::new(move |game: &mut Game| roster::pause(game, actor)));
+                        }
+                        if entry.paused && ui.button("Resume").clicked() {
+                            app.queue_act(Box::new(move |game: &mut Game| roster::resume(game, actor)));
+                        }
+                        if ui.button("Terminate").on_hover_text("Despawn it, with whatever it carries").clicked() {
+                            app.queue_act(Box::new(move |game: &mut Game| roster::terminate(game, actor)));
+                        }
+                    });
+                });
+            }
+        });
+    });
+}
@@ -0,0 +1,17 @@
+use crate::{app::Application, game_state::game::Game, save};
+
+pub fn show(app: &mut Application, ctx: &egui::Context) {
+    egui::Window::new("Save/Load").show(ctx, |ui| {
+        ui.label(format!("Save file: {}", save::DEFAULT_SAVE_PATH));
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                app.queue_act(Box::new(|game: &mut Game| {
+                    save::save_to_file(game, save::DEFAULT_SAVE_PATH)
+                }));
+            }
+            if ui.button("Load").clicked() {
+                app.load(save::DEFAULT_SAVE_PATH);
+            }
+        });
+    });
+}
@@ -1,7 +1,7 @@
 use std::f32;
 
 use egui::{
-    emath::Rot2, epaint::{CircleShape, RectShape, Shape}, pos2, Color32, Mesh, Pos2, Rect, Rounding, Stroke, TextureOptions, Vec2
+    emath::Rot2, epaint::{CircleShape, RectShape, Shape}, pos2, Align2, Color32, FontId, Mesh, Pos2, Rect, Rounding, Stroke, TextureOptions, Vec2
 };
 
 use crate::{
@@ -11,6 +11,8 @@ use crate::{
         game::Game,
         world::{FloorTile, World, WorldCell},
     },
+    action::SubAction,
+    preview::PreviewStep,
     static_data::AppearanceDefiniton,
 };
 
@@ -18,6 +20,8 @@ pub struct WorldWindowWidget<'a> {
     pub world: &'a World,
     pub center: Coordinate,
     pub show_cursor: bool,
+    // Predicted steps of a clone, drawn over the world.
+    pub preview: &'a [PreviewStep],
 }
 
 impl<'a> WorldWindowWidget<'a> {
@@ -28,6 +32,7 @@ impl<'a> WorldWindowWidget<'a> {
                 .get_player_coords()
                 .unwrap_or(&Coordinate { x: 0, y: 0 }),
             show_cursor: false,
+            preview: &[],
         }
     }
 }
@@ -65,12 +70,103 @@ fn object_to_shape(
             log::log!(log::Level::Error, "{}", e);
         }
     }
-    Shape::Rect(RectShape::new(
+    // No texture: draw the glyph for this orientation instead of rotating.
+    let background = Shape::Rect(RectShape::new(
         area.scale_from_center(scale),
         Rounding::ZERO,
         Color32::DARK_RED,
         Stroke::NONE,
-    ))
+    ));
+    let glyph = ctx.fonts(|fonts| {
+        Shape::text(
+            fonts,
+            area.center(),
+            Align2::CENTER_CENTER,
+            descriptor.glyph_facing(orientation),
+            FontId::monospace(area.height() * scale * 0.8),
+            Color32::WHITE,
+        )
+    });
+    Shape::Vec(vec![background, glyph])
+}
+
+// Arrow from the center of the cell towards its facing edge.
+fn facing_arrow(area: Rect, orientation: AbsoluteDirection) -> Vec<Shape> {
+    let dir = match orientation {
+        AbsoluteDirection::N => Vec2 { x: 0.0, y: -1.0 },
+        AbsoluteDirection::E => Vec2 { x: 1.0, y: 0.0 },
+        AbsoluteDirection::S => Vec2 { x: 0.0, y: 1.0 },
+        AbsoluteDirection::W => Vec2 { x: -1.0, y: 0.0 },
+    };
+    let length = area.width() * 0.35;
+    let tip = area.center() + dir * length;
+    let tail = area.center() - dir * length;
+    let side = dir.rot90() * length * 0.5;
+    let stroke = Stroke::new(2.0, Color32::WHITE);
+    vec![
+        Shape::line_segment([tail, tip], stroke),
+        Shape::line_segment([tip, tip - dir * length * 0.5 + side], stroke),
+        Shape::line_segment([tip, tip - dir * length * 0.5 - side], stroke),
+    ]
+}
+
+// Short marker for what a previewed step does.
+fn action_glyph(action: &SubAction) -> &'static str {
+    match action {
+        SubAction::Move => "",
+        SubAction::Take => "T",
+        SubAction::Drop(_) => "D",
+        SubAction::Use(_) => "U",
+        SubAction::ActivateBuilding => "A",
+        SubAction::Insert(_) => "I",
+        SubAction::Extract => "E",
+        SubAction::Craft(_) => "C",
+        SubAction::Wait => "W",
+        // Control steps are resolved before acting, so they never show up in a preview.
+        SubAction::Jump(_) | SubAction::JumpIf(_, _) | SubAction::Repeat(_, _) | SubAction::Call(_) => "",
+    }
+}
+
+// Ghost of a clone's predicted visits to one cell. Steps are numbered from 1, as in the editor.
+fn preview_shapes(ctx: &egui::Context, area: Rect, steps: &[&PreviewStep]) -> Vec<Shape> {
+    let Some(last) = steps.last() else {
+        return Vec::new();
+    };
+    let failed = steps.iter().any(|s| s.failed);
+    let stroke = if failed {
+        Stroke::new(3.0, Color32::RED)
+    } else {
+        Stroke::NONE
+    };
+    let mut ret = vec![Shape::Rect(RectShape::new(
+        area.shrink(2.0),
+        Rounding::ZERO,
+        Color32::from_white_alpha(40),
+        stroke,
+    ))];
+    ret.extend(facing_arrow(area, last.facing));
+
+    let numbers: Vec<String> = steps.iter().map(|s| (s.step + 1).to_string()).collect();
+    let glyph = if failed { "✗" } else { action_glyph(&last.action) };
+    ctx.fonts(|fonts| {
+        ret.push(Shape::text(
+            fonts,
+            area.left_top(),
+            Align2::LEFT_TOP,
+            numbers.join(","),
+            FontId::proportional(area.height() * 0.3),
+            Color32::WHITE,
+        ));
+        ret.push(Shape::text(
+            fonts,
+            area.right_bottom(),
+            Align2::RIGHT_BOTTOM,
+            glyph,
+            FontId::monospace(area.height() * 0.4),
+            if failed { Color32::RED } else { Color32::WHITE },
+        ));
+    });
+    ret
 }
 
 impl WorldCell<'_> {
@@ -95,8 +191,11 @@ impl WorldCell<'_> {
                 &building.definition.appearance,
                 area,
                 0.9,
-                AbsoluteDirection::S,
+                building.facing,
             ));
+            if building.definition.appearance.arrow.unwrap_or(false) {
+                ret.extend(facing_arrow(area, building.facing));
+            }
         }
 
         if let Some(item) = self.items[0] {
@@ -157,6 +256,9 @@ impl WorldWindowWidget<'_> {
 
                     ret.extend(cell.as_shape(&ctx, sub_area));
                 }
+                let visits: Vec<&PreviewStep> =
+                    self.preview.iter().filter(|s| s.location == coord).collect();
+                ret.extend(preview_shapes(ctx, sub_area, &visits));
             }
         }
         if self.show_cursor {
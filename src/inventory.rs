@@ -1,6 +1,7 @@
 //! Item and Inventory datatype definitions.
 
 use crate::recording::db::RecordingId;
+use crate::eventqueue::Deployment;
 use crate::error::{Result, Status};
 use crate::static_data::ObjectDescriptor;
 
@@ -9,6 +10,8 @@ pub struct Item {
     pub definition: &'static ObjectDescriptor, 
     pub quantity: u16,
     pub recording: Option<RecordingId>,
+    // Only used by cloners.
+    pub deployment: Deployment,
 }
 
 impl Item {
@@ -17,6 +20,7 @@ impl Item {
             definition,
             quantity,
             recording: None,
+            deployment: Deployment::default(),
         }
     }
 
@@ -25,6 +29,7 @@ impl Item {
             definition,
             quantity: 1,
             recording: Some(recordingid),
+            deployment: Deployment::default(),
         }
     }
 }
@@ -38,6 +43,10 @@ pub struct BasicInventory {
 }
 
 impl BasicInventory {
+    pub fn from_items(items: [Option<Item>; 5]) -> BasicInventory {
+        BasicInventory { items }
+    }
+
     // When made generic, this should be a vec or something
     pub fn get_items(&self) -> &[Option<Item>; 5] {
         &self.items
@@ -80,3 +89,44 @@ impl BasicInventory {
         Err(Status::ActionFail("no such item in inventory"))
     }
 }
+
+// A container with a configurable number of slots. Used for building storage.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct SlotInventory {
+    items: Vec<Option<Item>>,
+}
+
+impl SlotInventory {
+    pub fn new(slots: usize) -> SlotInventory {
+        SlotInventory {
+            items: vec![None; slots],
+        }
+    }
+
+    pub fn from_items(items: Vec<Option<Item>>) -> SlotInventory {
+        SlotInventory { items }
+    }
+
+    pub fn get_items(&self) -> &[Option<Item>] {
+        &self.items
+    }
+
+    pub fn insert(&mut self, new_item: Item) -> Result<()> {
+        for i in &mut self.items {
+            if i.is_none() {
+                *i = Some(new_item);
+                return Ok(());
+            }
+        }
+        Err(Status::ActionFail("no space in inventory"))
+    }
+
+    pub fn remove_idx(&mut self, idx: usize) -> Option<Item> {
+        self.items.get_mut(idx)?.take()
+    }
+
+    // Removes the item in the lowest occupied slot.
+    pub fn take_first(&mut self) -> Option<Item> {
+        self.items.iter_mut().find_map(|i| i.take())
+    }
+}
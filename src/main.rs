@@ -11,15 +11,30 @@ mod static_data;
 
 mod app;
 mod buildings;
+mod eventlog;
 mod eventqueue;
+mod headless;
+mod history;
 mod interface_egui;
 mod paradox;
+mod preview;
 mod recording;
+mod roster;
+mod save;
 mod score;
 mod worldgen;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--headless") {
+        if let Err(e) = headless::run(&args) {
+            eprintln!("{:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
         "Clone Factory",
@@ -1,5 +1,5 @@
 use crate::datatypes::Coordinate;
-use crate::engine::update::{Delta, UpdatableContainer, UpdatableContainerDelta};
+use crate::engine::update::{UpdatableContainer, UpdatableContainerDelta};
 use crate::engine::worldlayer::WorldLayer;
 use crate::error::{Result, Status::Error};
 use crate::game_state::game::Game;
@@ -8,12 +8,13 @@ use crate::{engine::tracking_worldlayer::TrackableId, game_state::game::GameUpda
 #[derive(PartialEq, Debug, Clone)]
 pub struct Paradox(pub f64);
 
+// Adds paradox at an actor's location, on top of a pending update. Returns whether the actor survives.
 pub fn update_actor_paradox(
     actor: TrackableId,
     increment: f64,
     game: &Game,
-) -> Result<(GameUpdate, bool)> {
-    let mut update = GameUpdate::new();
+    update: &mut GameUpdate,
+) -> Result<bool> {
     let location = *update
         .world
         .actor_updates
@@ -49,7 +50,7 @@ pub fn update_actor_paradox(
         .set(&location, &Paradox(new_background))?;
 
     update.world.actor_updates.set(&location, &Some(actor))?;
-    Ok((update, survive))
+    Ok(survive)
 }
 
 pub fn diffuse_paradox(layer: &mut WorldLayer<Paradox>) {
@@ -0,0 +1,147 @@
+//! Predicting where a clone will go before deploying it.
+//!
+//! A clone is placed on a scratch copy of the game, as a cloner would place it, and its recording
+//! is played out step by step. The rest of the world keeps taking turns around it.
+
+use crate::action::{self, SubAction};
+use crate::actor::Actor;
+use crate::datatypes::Coordinate;
+use crate::direction::AbsoluteDirection;
+use crate::engine::update::{Delta, UpdatableContainer};
+use crate::error::{
+    Result,
+    Status::{ActionFail, Error, OutOfBounds},
+};
+use crate::eventqueue::ActorEvent;
+use crate::game_state::game::Game;
+use crate::recording::db::RecordingId;
+
+// Recordings can loop forever, so only this many steps are predicted.
+pub const MAX_PREVIEW_STEPS: usize = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreviewStep {
+    // Index into the recording being played, which may be a called one.
+    pub step: usize,
+    // Where the clone is after the step, facing the way it acted.
+    pub location: Coordinate,
+    pub facing: AbsoluteDirection,
+    pub action: SubAction,
+    // The clone is predicted to fail this step. Nothing after it is predicted.
+    pub failed: bool,
+}
+
+pub fn predict(game: &Game, recording_id: RecordingId) -> Result<Vec<PreviewStep>> {
+    let mut steps = Vec::new();
+    // The id may be left over from another game, e.g. one replaced by loading a save.
+    if !game.recordings.recordings.contains(recording_id) {
+        return Err(ActionFail("no such recording"));
+    }
+    let recording = game.recordings.get(recording_id);
+    if recording.len() == 0 {
+        return Ok(steps);
+    }
+    let mut scratch = game.scratch_copy();
+
+    // Placed in front of the player, facing the same way, as execute_use_cloner does.
+    let player = *scratch.get_player_actor()?;
+    let player_location = *scratch.get_player_coords()?;
+    let start = Coordinate::as_offset(Coordinate { x: 0, y: 1 }, player_location, player.facing);
+    match scratch.world.actors.get(&start) {
+        Err(OutOfBounds) => return Err(ActionFail("destination out of bounds")),
+        Err(err) => return Err(err),
+        Ok(Some(_)) => return Err(ActionFail("destination occupied")),
+        Ok(None) => {}
+    }
+    let clone_id = scratch.world.actors.mut_get_next_id();
+    let descriptor = scratch
+        .data
+        .actors
+        .get("clone")
+        .ok_or(Error("unable to get clone definition"))?;
+    let mut clone = Actor::from_recording(descriptor, clone_id, recording);
+    clone.facing = player.facing;
+    scratch.world.actors.mut_set(&start, &Some(clone))?;
+
+    let mut evt = ActorEvent::new(clone_id, recording_id);
+    while steps.len() < MAX_PREVIEW_STEPS {
+        if !scratch.resolve_control_steps(&mut evt)? {
+            break;
+        }
+        let step = scratch.recordings.get(evt.recording).at(evt.recording_idx);
+        for _ in 1..action::action_duration(clone_id, step, &scratch)? {
+            scratch.end_turn()?;
+        }
+
+        let failed = match action::execute_action(clone_id, step, &scratch) {
+            Ok(update) => {
+                update.apply(&mut scratch)?;
+                false
+            }
+            Err(ActionFail(_)) => true,
+            Err(err) => return Err(err),
+        };
+        let location = *scratch.world.actors.get_location(&clone_id)?;
+        let clone = scratch
+            .world
+            .actors
+            .get(&location)?
+            .ok_or(Error("No actor at expected coordinates"))?;
+        steps.push(PreviewStep {
+            step: evt.recording_idx,
+            location,
+            facing: clone.facing.rotate(&step.direction),
+            action: step.action,
+            failed,
+        });
+        if failed || !evt.advance(&scratch.recordings.recordings, evt.recording_idx + 1) {
+            break;
+        }
+
+        scratch.end_turn()?;
+        // Stop if the clone didn't survive the turn, e.g. from paradox.
+        if scratch.world.actors.get_location(&clone_id).is_err() {
+            break;
+        }
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::Action;
+    use crate::direction::{Direction, RelativeDirection};
+    use crate::recording::Recording;
+    use crate::static_data::Data;
+
+    use super::*;
+
+    #[test]
+    fn predict_path() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 4 }, data);
+        game.spawn(&Coordinate { x: 0, y: 0 }).unwrap();
+        let step = |action| Action {
+            direction: Direction::Relative(RelativeDirection::F),
+            action,
+        };
+        let id = game.recordings.recordings.register_recording(Recording {
+            command_list: vec![step(SubAction::Move), step(SubAction::Move), step(SubAction::Move)],
+            ..Recording::blank()
+        });
+
+        // The clone starts at y = 1, so the third move leaves the world.
+        let steps = predict(&game, id).unwrap();
+        let locations: Vec<Coordinate> = steps.iter().map(|s| s.location).collect();
+        assert_eq!(locations, [Coordinate { x: 0, y: 2 }, Coordinate { x: 0, y: 3 }, Coordinate { x: 0, y: 3 }]);
+        assert_eq!(steps.iter().position(|s| s.failed), Some(2));
+
+        // The real game is untouched.
+        assert!(game.world.actors.get(&Coordinate { x: 0, y: 1 }).unwrap().is_none());
+        assert_eq!(game.score.turn, 0);
+
+        // An id from another game, e.g. after loading a save, isn't looked up.
+        let other = Game::new(Coordinate { x: 1, y: 4 }, data);
+        assert!(predict(&other, id).is_err());
+    }
+}
@@ -3,18 +3,30 @@
 // This probably doesn't need an Updateable, as only the player will ever modify it
 // and therefore will never be multithreaded.
 
+use std::fmt;
+
+use serde_derive::{Deserialize, Serialize};
+
 use super::Recording;
 
 
+#[derive(Clone)]
 pub struct RecordingDb {
     recordings: Vec<Recording>,
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct RecordingId {
     idx: usize,
 }
 
+// Shown to the player to tell apart recordings with the same name.
+impl fmt::Display for RecordingId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.idx)
+    }
+}
+
 impl RecordingDb {
     pub fn register_recording(&mut self, recording: Recording) -> RecordingId {
         self.recordings.push(recording);
@@ -34,4 +46,21 @@ impl RecordingDb {
     pub fn get(&self, id: RecordingId) -> &Recording {
         &self.recordings[id.idx]
     }
+
+    pub fn get_all(&self) -> &[Recording] {
+        &self.recordings
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = RecordingId> {
+        (0..self.recordings.len()).map(|idx| RecordingId { idx })
+    }
+
+    pub fn contains(&self, id: RecordingId) -> bool {
+        id.idx < self.recordings.len()
+    }
+
+    // Rebuild a db from a list of recordings, e.g. from a save file. Ids are list indices.
+    pub fn from_recordings(recordings: Vec<Recording>) -> RecordingDb {
+        RecordingDb { recordings }
+    }
 }
\ No newline at end of file
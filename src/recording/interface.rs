@@ -4,25 +4,22 @@
 // A function that can be a plugin into player_action
 // A function that can be a plugin into npc turns
 // A menu
-//  Start record (select and consume a blank recorder; different recorders have different features)
 //  End record (select loop/die), spawns into inventory
-//  View saved recordings
-//  Duplicate saved recording (consumes a crystal of equal or greater tier.)
 
 
 
 use super::{
     db::{RecordingDb, RecordingId},
-    Recording,
+    FallbackPolicy, Recording,
 };
 
 use crate::{
-    direction::{AbsoluteDirection, Direction},
+    direction::{AbsoluteDirection, Direction, RelativeDirection},
     engine::update::{Delta, UpdatableContainer},
 };
 
 use crate::{
-    action::Action,
+    action::{self, Action, SubAction},
     error::{
          Result,
         Status::{ActionFail, Error},
@@ -30,17 +27,38 @@ use crate::{
 };
 use crate::{
     devtools,
+    eventqueue::Deployment,
     game_state::game::Game,
-    inventory::Item,
+    inventory::{BasicInventory, Item},
 };
 
 
+// Name for a recording saved without one, numbered by its place in the db.
+fn default_name(game: &Game) -> String {
+    format!("Recording {}", game.recordings.recordings.get_all().len())
+}
+
+// Takes the ingredients of a recorder from the player. Cloners that aren't made by recording cost the same.
+fn pay_for_cloner(game: &mut Game) -> Result<()> {
+    let recipe = game
+        .data
+        .recipes
+        .get("recorder")
+        .ok_or(Error("unable to get recorder recipe"))?;
+    let mut player = game.get_player_actor().cloned()?;
+    let coords = *game.get_player_coords()?;
+    action::pay_ingredients(recipe, &mut player.inventory, game.data)?;
+    game.world.actors.mut_set(&coords, &Some(player))
+}
+
 // TODO: implement update struct so that functions operating on this don't need a refcell, maybe?
+#[derive(Clone)]
 pub struct RecordingModule {
     pub recordings: RecordingDb,
     pub current_recording: Option<Recording>,
     pub temp_item: Option<Item>,
-    last_player_facing: AbsoluteDirection,
+    // Saved alongside the rest of the module, as recordings are stored relative to this.
+    pub last_player_facing: AbsoluteDirection,
 }
 
 impl RecordingModule {
@@ -49,7 +67,7 @@ impl RecordingModule {
             recordings: RecordingDb::new(),
             current_recording: None,
             temp_item: None,
-            // Could pose an issue with save/load, as this is state. player must perform an action before recording anything.
+            // Player must perform an action before recording anything.
             last_player_facing: AbsoluteDirection::N,
         }
     }
@@ -75,6 +93,59 @@ impl RecordingModule {
         }
     }
 
+    // A failed action leaves the player facing the same way, so later steps stay relative to the old facing.
+    pub fn append_failed(&mut self, action: Action) {
+        let facing = self.last_player_facing;
+        self.append(action);
+        self.last_player_facing = facing;
+    }
+
+    // Fails once the recording holds as many steps as its recorder allows, so nothing more is
+    // recorded that would stop it from being ended.
+    pub fn check_room(&self) -> Result<()> {
+        let full = self.current_recording.as_ref().is_some_and(|rec| {
+            rec.limits()
+                .and_then(|l| l.max_steps)
+                .is_some_and(|max| rec.len() >= max)
+        });
+        if full {
+            return Err(ActionFail("recording is full, end it first"));
+        }
+        Ok(())
+    }
+
+    pub fn records_failures(&self) -> bool {
+        self.current_recording
+            .as_ref()
+            .is_some_and(|rec| rec.record_failures)
+    }
+
+    pub fn set_record_failures(game: &mut Game, record_failures: bool) -> Result<()> {
+        let recording = game
+            .recordings
+            .current_recording
+            .as_mut()
+            .ok_or(Error("Called set_record_failures without a recording"))?;
+        if record_failures && recording.limits().is_some_and(|l| !l.record_failures) {
+            return Err(ActionFail("this recorder can't keep failed actions"));
+        }
+        recording.record_failures = record_failures;
+        game.history.clear();
+        Ok(())
+    }
+
+    // What the recording's steps do when they fail, unless a step has its own fallback.
+    pub fn set_fallback(game: &mut Game, fallback: FallbackPolicy) -> Result<()> {
+        let recording = game
+            .recordings
+            .current_recording
+            .as_mut()
+            .ok_or(Error("Called set_fallback without a recording"))?;
+        recording.fallback = fallback;
+        game.history.clear();
+        Ok(())
+    }
+
     pub fn load_recording(&mut self, recording: Recording) -> RecordingId {
         self.recordings.register_recording(recording)
     }
@@ -92,18 +163,66 @@ impl RecordingModule {
             .inventory
             .remove_idx(idx)
             .ok_or(ActionFail("No item in slot"))?;
-        // TODO clean up this check.
-        if item.definition.name != "recorder" {
+        if item.definition.recorder.is_none() {
             return Err(ActionFail("Item is not an empty recorder"));
         }
 
         game.world.actors.mut_set(&coords, &Some(player))?;
-        game.recordings.current_recording = Some(Recording::from_creator(&player));
+        game.recordings.current_recording = Some(Recording {
+            recorder: Some(item.definition),
+            ..Recording::from_creator(&player)
+        });
+        game.history.clear();
+        Ok(())
+    }
+
+    // Adds a step that repeats the steps from start up to now, count times in total.
+    pub fn add_repeat(game: &mut Game, start: usize, count: u32) -> Result<()> {
+        game.recordings.check_room()?;
+        let recording = game
+            .recordings
+            .current_recording
+            .as_mut()
+            .ok_or(Error("Called add_repeat without a recording"))?;
+        if start >= recording.len() {
+            return Err(ActionFail("no steps to repeat"));
+        }
+        if count == 0 {
+            return Err(ActionFail("repeat count must be at least one"));
+        }
+        recording.append(Action {
+            direction: Direction::Relative(RelativeDirection::F),
+            action: SubAction::Repeat(start, count),
+        });
+        game.history.clear();
+        Ok(())
+    }
+
+    // Adds a step that plays a saved recording through once, then carries on with this one.
+    pub fn add_call(game: &mut Game, callee: RecordingId) -> Result<()> {
+        game.recordings.check_room()?;
+        if !game.recordings.recordings.contains(callee) {
+            return Err(Error("Called add_call with an unknown recording"));
+        }
+        if game.recordings.get(callee).len() == 0 {
+            return Err(ActionFail("recording to call is empty"));
+        }
+        let recording = game
+            .recordings
+            .current_recording
+            .as_mut()
+            .ok_or(Error("Called add_call without a recording"))?;
+        recording.append(Action {
+            direction: Direction::Relative(RelativeDirection::F),
+            action: SubAction::Call(callee),
+        });
+        game.history.clear();
         Ok(())
     }
 
-    // End recording.
-    pub fn end_record(game: &mut Game, should_loop: bool) -> Result<()> {
+    // End recording. Recordings that don't loop are played repeat_count times.
+    // A blank name is replaced with a numbered one.
+    pub fn end_record(game: &mut Game, should_loop: bool, repeat_count: u32, name: &str) -> Result<()> {
         let recording = game
             .recordings
             .current_recording
@@ -116,11 +235,116 @@ impl RecordingModule {
             .ok_or(Error("unable to get basic cloner definition"))?;
         let mut recording = recording.clone();
         recording.should_loop = should_loop;
+        recording.repeat_count = repeat_count.max(1);
+        recording.created_turn = game.score.turn;
+        recording.name = match name.trim() {
+            "" => default_name(game),
+            name => name.to_string(),
+        };
+        recording.check_limits()?;
 
         let id = game.recordings.recordings.register_recording(recording);
         let new_cloner = Item::new_cloner(cloner_def, id);
         game.recordings.temp_item = Some(new_cloner);
         game.recordings.current_recording = None;
+        game.history.clear();
+        Ok(())
+    }
+
+    // Store a recording that wasn't recorded here, e.g. an edited copy or an imported one, under a
+    // new id with a cloner ready to take. Any recording it was copied from is left as it is.
+    // Costs the same as crafting a recorder.
+    pub fn save_new(game: &mut Game, mut recording: Recording) -> Result<()> {
+        if game.recordings.temp_item.is_some() {
+            return Err(ActionFail("take the waiting cloner first"));
+        }
+        if recording.len() == 0 {
+            return Err(ActionFail("recording has no steps"));
+        }
+        recording.check_steps()?;
+        recording.check_limits()?;
+        for step in &recording.command_list {
+            if let SubAction::Call(callee) = step.action {
+                if !game.recordings.recordings.contains(callee) {
+                    return Err(Error("New recording calls an unknown recording"));
+                }
+            }
+        }
+        let cloner_def = game
+            .data
+            .items
+            .get("basic_cloner")
+            .ok_or(Error("unable to get basic cloner definition"))?;
+        pay_for_cloner(game)?;
+
+        recording.created_turn = game.score.turn;
+        if recording.name.trim().is_empty() {
+            recording.name = default_name(game);
+        }
+        let id = game.recordings.recordings.register_recording(recording);
+        game.recordings.temp_item = Some(Item::new_cloner(cloner_def, id));
+        game.history.clear();
+        Ok(())
+    }
+
+    // Store a recording imported from text. The player must carry the recorder it names, and pays for
+    // its starting inventory as well as for the cloner.
+    pub fn import(game: &mut Game, recording: Recording) -> Result<()> {
+        let recorder = recording
+            .recorder
+            .ok_or(ActionFail("imported recordings must name their recorder"))?;
+        let before = *game.get_player_actor()?;
+        let coords = *game.get_player_coords()?;
+        let items = before.inventory.get_items();
+        if !items.iter().flatten().any(|item| item.definition.name == recorder.name) {
+            return Err(ActionFail("you need the recorder the recording was made with"));
+        }
+
+        let mut player = before;
+        for item in recording.inventory.get_items().iter().flatten() {
+            for _ in 0..item.quantity {
+                player.inventory.remove(Item::new(item.definition, 1))?;
+            }
+        }
+        game.world.actors.mut_set(&coords, &Some(player))?;
+        if let Err(err) = Self::save_new(game, recording) {
+            game.world.actors.mut_set(&coords, &Some(before))?;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    // Make another cloner for a stored recording. Costs the same as crafting a recorder.
+    pub fn duplicate(game: &mut Game, id: RecordingId) -> Result<()> {
+        if game.recordings.temp_item.is_some() {
+            return Err(ActionFail("take the waiting cloner first"));
+        }
+        if !game.recordings.recordings.contains(id) || game.recordings.get(id).len() == 0 {
+            return Err(ActionFail("no recording to duplicate"));
+        }
+        let cloner_def = game
+            .data
+            .items
+            .get("basic_cloner")
+            .ok_or(Error("unable to get basic cloner definition"))?;
+        pay_for_cloner(game)?;
+        game.recordings.temp_item = Some(Item::new_cloner(cloner_def, id));
+        game.history.clear();
+        Ok(())
+    }
+
+    // Sets when clones from the cloner in the player's given slot start acting, and whether they play again.
+    pub fn set_deployment(game: &mut Game, idx: usize, deployment: Deployment) -> Result<()> {
+        let mut player = game.get_player_actor().cloned()?;
+        let coords = *game.get_player_coords()?;
+        let mut items = *player.inventory.get_items();
+        let Some(cloner) = items.get_mut(idx).and_then(|i| i.as_mut()).filter(|i| i.recording.is_some()) else {
+            return Err(ActionFail("not a cloner"));
+        };
+        cloner.deployment = deployment;
+        player.inventory = BasicInventory::from_items(items);
+        game.world.actors.mut_set(&coords, &Some(player))?;
+        game.history.clear();
         Ok(())
     }
 
@@ -131,6 +355,8 @@ impl RecordingModule {
             .temp_item
             .ok_or(ActionFail("no cloner to take"))?;
         let location = game.get_player_coords()?;
-        devtools::grant_item(item, *location, &game)?.apply(game)
+        devtools::grant_item(item, *location, &game)?.apply(game)?;
+        game.history.clear();
+        Ok(())
     }
 }
@@ -1,15 +1,67 @@
 //! A Recording is a sequence of replayable actions.
 
-use crate::{action::Action, actor::Actor, inventory::BasicInventory};
+use std::collections::HashMap;
+
+use crate::{
+    action::{Action, SubAction},
+    actor::Actor,
+    engine::tracking_worldlayer::TrackableId,
+    error::{Result, Status::ActionFail},
+    inventory::BasicInventory,
+    static_data::{ObjectDescriptor, RecorderDefinition},
+};
 
 pub mod db;
 pub mod interface;
+pub mod text;
+
+// Number of times a step with the Retry policy is attempted again before it counts as failed.
+pub const MAX_RETRIES: u32 = 3;
+
+// Number of control steps a clone may follow in one turn, so that a jump loop can't hang the game.
+pub const MAX_CONTROL_STEPS: usize = 16;
+
+// Number of recordings that can be waiting on a call at once.
+pub const MAX_CALL_DEPTH: usize = 8;
+
+// What a clone does when a recorded step fails.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FallbackPolicy {
+    // Generate paradox and move on to the next step.
+    Paradox,
+    // Try the step again next turn, up to MAX_RETRIES times, then fall back to Paradox.
+    Retry,
+    // Try the step again every turn until it succeeds.
+    Wait,
+    // Move on to the next step without generating paradox.
+    Skip,
+    // Perform another action instead. Generates paradox if that fails too.
+    Alternate(Action),
+    // Remove the clone without generating paradox.
+    Despawn,
+}
 
 #[derive(Clone)]
 pub struct Recording {
     pub command_list: Vec<Action>,
     pub inventory: BasicInventory,
-    pub should_loop: bool
+    pub should_loop: bool,
+    // Times to play the recording through, if it doesn't loop forever.
+    pub repeat_count: u32,
+    // Applies to every step without an override in step_fallbacks.
+    pub fallback: FallbackPolicy,
+    // Per-step overrides, keyed by index into command_list.
+    pub step_fallbacks: HashMap<usize, FallbackPolicy>,
+    // Keep the player's failed actions while recording, for clones to attempt.
+    pub record_failures: bool,
+    pub name: String,
+    pub note: String,
+    // Recorder item the recording was made with. Its limits also apply when editing.
+    pub recorder: Option<&'static ObjectDescriptor>,
+    // Actor that made the recording. None for recordings that weren't recorded, e.g. the blank one.
+    pub creator: Option<TrackableId>,
+    // Turn the recording was stored on.
+    pub created_turn: i64,
 }
 
 impl Recording {
@@ -18,17 +70,30 @@ impl Recording {
             command_list: Vec::new(),
             inventory: Default::default(),
             should_loop: false,
+            repeat_count: 1,
+            fallback: FallbackPolicy::Paradox,
+            step_fallbacks: HashMap::new(),
+            record_failures: false,
+            name: String::new(),
+            note: String::new(),
+            recorder: None,
+            creator: None,
+            created_turn: 0,
         }
     }
 
     pub fn from_creator(actor: &Actor) -> Recording {
         Recording {
-            command_list: Vec::new(),
             inventory: actor.inventory,
-            should_loop: false
+            creator: Some(actor.actor_id),
+            ..Recording::blank()
         }
     }
 
+    pub fn fallback_at(&self, idx: usize) -> FallbackPolicy {
+        *self.step_fallbacks.get(&idx).unwrap_or(&self.fallback)
+    }
+
     pub fn at(&self, idx: usize) -> Action {
         self.command_list[idx % self.command_list.len()]
     }
@@ -40,5 +105,143 @@ impl Recording {
     pub fn len(&self) -> usize {
         self.command_list.len()
     }
+
+    // Editing. Control step targets and step fallbacks keep pointing at the same steps.
+
+    pub fn insert_step(&mut self, idx: usize, action: Action) {
+        self.remap_steps(|i| if i >= idx { i + 1 } else { i });
+        self.command_list.insert(idx, action);
+    }
+
+    // References to the removed step now point at the step after it.
+    pub fn remove_step(&mut self, idx: usize) {
+        self.command_list.remove(idx);
+        self.step_fallbacks.remove(&idx);
+        self.remap_steps(|i| if i > idx { i - 1 } else { i });
+    }
+
+    pub fn move_step(&mut self, from: usize, to: usize) {
+        let action = self.command_list.remove(from);
+        self.command_list.insert(to, action);
+        self.remap_steps(|i| {
+            if i == from {
+                to
+            } else if from < to && from < i && i <= to {
+                i - 1
+            } else if to < from && to <= i && i < from {
+                i + 1
+            } else {
+                i
+            }
+        });
+    }
+
+    fn remap_steps(&mut self, f: impl Fn(usize) -> usize) {
+        for step in self.command_list.iter_mut() {
+            step.action = match step.action {
+                SubAction::Jump(target) => SubAction::Jump(f(target)),
+                SubAction::JumpIf(condition, target) => SubAction::JumpIf(condition, f(target)),
+                SubAction::Repeat(start, count) => SubAction::Repeat(f(start), count),
+                other => other,
+            };
+        }
+        self.step_fallbacks = self.step_fallbacks.drain().map(|(k, v)| (f(k), v)).collect();
+    }
+
+    pub fn limits(&self) -> Option<&'static RecorderDefinition> {
+        self.recorder.and_then(|def| def.recorder.as_ref())
+    }
+
+    // Checks the recording against what its recorder can do. Recordings without a recorder have no limits.
+    pub fn check_limits(&self) -> Result<()> {
+        let Some(limits) = self.limits() else {
+            return Ok(());
+        };
+        if limits.max_steps.is_some_and(|max| self.len() > max) {
+            return Err(ActionFail("too many steps for this recorder"));
+        }
+        if self.should_loop && !limits.allow_loop {
+            return Err(ActionFail("this recorder can't loop"));
+        }
+        if self.record_failures && !limits.record_failures {
+            return Err(ActionFail("this recorder can't keep failed actions"));
+        }
+        let conditional = self
+            .command_list
+            .iter()
+            .any(|step| matches!(step.action, SubAction::JumpIf(_, _)));
+        if conditional && !limits.allow_conditionals {
+            return Err(ActionFail("this recorder can't hold conditional steps"));
+        }
+        Ok(())
+    }
+
+    // Checks that control steps make sense, e.g. after editing.
+    // Jumping to the step past the end finishes the recording.
+    pub fn check_steps(&self) -> Result<()> {
+        for (idx, step) in self.command_list.iter().enumerate() {
+            match step.action {
+                SubAction::Jump(target) | SubAction::JumpIf(_, target) if target > self.len() => {
+                    return Err(ActionFail("jump past the end of the recording"));
+                }
+                SubAction::Repeat(start, _) if start >= idx => {
+                    return Err(ActionFail("repeat step must come after its block"));
+                }
+                SubAction::Repeat(_, 0) => {
+                    return Err(ActionFail("repeat count must be at least one"));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::direction::{Direction, RelativeDirection};
+
+    use super::*;
+
+    fn step(action: SubAction) -> Action {
+        Action {
+            direction: Direction::Relative(RelativeDirection::F),
+            action,
+        }
+    }
+
+    #[test]
+    fn edit_steps() {
+        let mut recording = Recording {
+            command_list: vec![
+                step(SubAction::Move),
+                step(SubAction::Take),
+                step(SubAction::Repeat(0, 2)),
+                step(SubAction::Jump(1)),
+            ],
+            ..Recording::blank()
+        };
+        recording.step_fallbacks.insert(1, FallbackPolicy::Skip);
+
+        recording.insert_step(0, step(SubAction::Wait));
+        assert_eq!(recording.at(3).action, SubAction::Repeat(1, 2));
+        assert_eq!(recording.at(4).action, SubAction::Jump(2));
+        assert_eq!(recording.fallback_at(2), FallbackPolicy::Skip);
+
+        // Move Take to the end; the jump and fallback follow it.
+        recording.move_step(2, 4);
+        assert_eq!(recording.at(2).action, SubAction::Repeat(1, 2));
+        assert_eq!(recording.at(3).action, SubAction::Jump(4));
+        assert_eq!(recording.fallback_at(4), FallbackPolicy::Skip);
+        assert!(recording.check_steps().is_ok());
+
+        recording.remove_step(4);
+        assert_eq!(recording.at(3).action, SubAction::Jump(4));
+        assert!(recording.step_fallbacks.is_empty());
+        assert!(recording.check_steps().is_ok());
+
+        recording.move_step(2, 0);
+        assert!(recording.check_steps().is_err());
+    }
 }
 
@@ -0,0 +1,467 @@
+//! A plain text format for recordings, for sharing them and keeping them in version control.
+//!
+//! One line per header or step. Blank lines and lines starting with `#` are ignored.
+//!
+//! ```text
+//! name patrol
+//! note Fetches ore from the chest.
+//! recorder looping_recorder
+//! loop                              (or: plays 3)
+//! inventory echo_crystal x2, -, raw_crystal
+//! fallback retry
+//! F move
+//! R take | skip
+//! craft recorder
+//! use 2
+//! jump_if carrying raw_crystal 2
+//! repeat 1 3
+//! call other recording
+//! ```
+//!
+//! Steps start with an optional direction (F, R, B, L, or N, E, S, W for absolute ones; F if left
+//! out), then the action. A fallback for the step can follow a `|`. Steps and inventory slots are
+//! numbered from 1, as in the game. Calls refer to other recordings by name. Cloners in the
+//! starting inventory are written as plain items, without their recording, so they can't be
+//! imported. Every imported recording must name its recorder.
+
+use std::fmt;
+
+use crate::{
+    action::{Action, Condition, SubAction},
+    direction::{AbsoluteDirection, Direction, RelativeDirection},
+    inventory::{BasicInventory, Item},
+    static_data::{Data, ObjectDescriptor},
+};
+
+use super::{db::RecordingDb, FallbackPolicy, Recording};
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    // Counted from 1. None for problems with the text as a whole, e.g. a missing header.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+type LineResult<T> = std::result::Result<T, String>;
+
+fn direction_text(direction: &Direction) -> &'static str {
+    match direction {
+        Direction::Relative(RelativeDirection::F) => "F",
+        Direction::Relative(RelativeDirection::R) => "R",
+        Direction::Relative(RelativeDirection::B) => "B",
+        Direction::Relative(RelativeDirection::L) => "L",
+        Direction::Absolute(AbsoluteDirection::N) => "N",
+        Direction::Absolute(AbsoluteDirection::E) => "E",
+        Direction::Absolute(AbsoluteDirection::S) => "S",
+        Direction::Absolute(AbsoluteDirection::W) => "W",
+    }
+}
+
+fn parse_direction(word: &str) -> Option<Direction> {
+    Some(match word {
+        "F" => Direction::Relative(RelativeDirection::F),
+        "R" => Direction::Relative(RelativeDirection::R),
+        "B" => Direction::Relative(RelativeDirection::B),
+        "L" => Direction::Relative(RelativeDirection::L),
+        "N" => Direction::Absolute(AbsoluteDirection::N),
+        "E" => Direction::Absolute(AbsoluteDirection::E),
+        "S" => Direction::Absolute(AbsoluteDirection::S),
+        "W" => Direction::Absolute(AbsoluteDirection::W),
+        _ => return None,
+    })
+}
+
+fn condition_text(condition: &Condition) -> String {
+    match condition {
+        Condition::FloorItemIs(item) => format!("floor_has {}", item.name),
+        Condition::InventoryHas(item) => format!("carrying {}", item.name),
+        Condition::TileAheadOccupied => "blocked".to_string(),
+        Condition::ParadoxAbove(threshold) => format!("paradox_above {}", threshold),
+    }
+}
+
+fn step_text(step: &Action, db: &RecordingDb, data: &Data) -> String {
+    let action = match step.action {
+        SubAction::Move => "move".to_string(),
+        SubAction::Take => "take".to_string(),
+        SubAction::Drop(slot) => format!("drop {}", slot + 1),
+        SubAction::Use(slot) => format!("use {}", slot + 1),
+        SubAction::ActivateBuilding => "activate".to_string(),
+        SubAction::Insert(slot) => format!("insert {}", slot + 1),
+        SubAction::Extract => "extract".to_string(),
+        SubAction::Craft(recipe) => {
+            format!("craft {}", data.recipe_key(recipe).map_or("", |k| k.as_str()))
+        }
+        SubAction::Wait => "wait".to_string(),
+        SubAction::Jump(target) => format!("jump {}", target + 1),
+        SubAction::JumpIf(condition, target) => {
+            format!("jump_if {} {}", condition_text(&condition), target + 1)
+        }
+        SubAction::Repeat(start, count) => format!("repeat {} {}", start + 1, count),
+        SubAction::Call(callee) => format!("call {}", db.get(callee).name),
+    };
+    format!("{} {}", direction_text(&step.direction), action)
+}
+
+fn fallback_text(policy: &FallbackPolicy, db: &RecordingDb, data: &Data) -> String {
+    match policy {
+        FallbackPolicy::Paradox => "paradox".to_string(),
+        FallbackPolicy::Retry => "retry".to_string(),
+        FallbackPolicy::Wait => "wait".to_string(),
+        FallbackPolicy::Skip => "skip".to_string(),
+        FallbackPolicy::Alternate(action) => format!("alternate {}", step_text(action, db, data)),
+        FallbackPolicy::Despawn => "despawn".to_string(),
+    }
+}
+
+fn inventory_text(inventory: &BasicInventory) -> String {
+    let mut slots: Vec<String> = inventory
+        .get_items()
+        .iter()
+        .map(|slot| match slot {
+            None => "-".to_string(),
+            Some(item) if item.quantity == 1 => item.definition.name.clone(),
+            Some(item) => format!("{} x{}", item.definition.name, item.quantity),
+        })
+        .collect();
+    while slots.last().is_some_and(|s| s == "-") {
+        slots.pop();
+    }
+    slots.join(", ")
+}
+
+pub fn to_text(recording: &Recording, db: &RecordingDb, data: &Data) -> String {
+    let mut lines = Vec::new();
+    if !recording.name.is_empty() {
+        lines.push(format!("name {}", recording.name));
+    }
+    // The format is line based, so notes are kept to one line.
+    let note = recording.note.lines().collect::<Vec<_>>().join(" ");
+    if !note.is_empty() {
+        lines.push(format!("note {}", note));
+    }
+    if let Some(recorder) = recording.recorder {
+        lines.push(format!("recorder {}", recorder.name));
+    }
+    if recording.should_loop {
+        lines.push("loop".to_string());
+    } else {
+        lines.push(format!("plays {}", recording.repeat_count));
+    }
+    let inventory = inventory_text(&recording.inventory);
+    if !inventory.is_empty() {
+        lines.push(format!("inventory {}", inventory));
+    }
+    if recording.fallback != FallbackPolicy::Paradox {
+        lines.push(format!("fallback {}", fallback_text(&recording.fallback, db, data)));
+    }
+    for (idx, step) in recording.command_list.iter().enumerate() {
+        let mut line = step_text(step, db, data);
+        if let Some(policy) = recording.step_fallbacks.get(&idx) {
+            line = format!("{} | {}", line, fallback_text(policy, db, data));
+        }
+        lines.push(line);
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn parse_number<T: std::str::FromStr>(word: &str, what: &str) -> LineResult<T> {
+    word.parse()
+        .map_err(|_| format!("expected {}, found \"{}\"", what, word))
+}
+
+// Step and slot numbers are written from 1.
+fn parse_index(word: &str, what: &str) -> LineResult<usize> {
+    match parse_number::<usize>(word, what)? {
+        0 => Err(format!("{} numbers start at 1", what)),
+        n => Ok(n - 1),
+    }
+}
+
+fn lookup_item(name: &str, data: &'static Data) -> LineResult<&'static ObjectDescriptor> {
+    data.items
+        .get(name)
+        .ok_or(format!("unknown item \"{}\"", name))
+}
+
+fn bad_arguments(words: &[&str]) -> String {
+    match words.first() {
+        None => "missing action".to_string(),
+        Some(word) => format!("wrong arguments for \"{}\"", word),
+    }
+}
+
+fn parse_condition(words: &[&str], data: &'static Data) -> LineResult<Condition> {
+    Ok(match words {
+        ["floor_has", item] => Condition::FloorItemIs(lookup_item(item, data)?),
+        ["carrying", item] => Condition::InventoryHas(lookup_item(item, data)?),
+        ["blocked"] => Condition::TileAheadOccupied,
+        ["paradox_above", level] => Condition::ParadoxAbove(parse_number(level, "paradox level")?),
+        [kind, ..] if !CONDITIONS.contains(kind) => return Err(format!("unknown condition \"{}\"", kind)),
+        _ => return Err(bad_arguments(words)),
+    })
+}
+
+const CONDITIONS: [&str; 4] = ["floor_has", "carrying", "blocked", "paradox_above"];
+
+const ACTIONS: [&str; 13] = [
+    "move", "take", "drop", "use", "activate", "insert", "extract", "craft", "wait", "jump", "jump_if",
+    "repeat", "call",
+];
+
+fn parse_step(words: &[&str], db: &RecordingDb, data: &'static Data) -> LineResult<Action> {
+    let (direction, words) = match words.first().and_then(|w| parse_direction(w)) {
+        Some(direction) => (direction, &words[1..]),
+        None => (Direction::Relative(RelativeDirection::F), words),
+    };
+    let action = match words {
+        ["move"] => SubAction::Move,
+        ["take"] => SubAction::Take,
+        ["drop", slot] => SubAction::Drop(parse_index(slot, "slot")?),
+        ["use", slot] => SubAction::Use(parse_index(slot, "slot")?),
+        ["activate"] => SubAction::ActivateBuilding,
+        ["insert", slot] => SubAction::Insert(parse_index(slot, "slot")?),
+        ["extract"] => SubAction::Extract,
+        ["craft", name] => SubAction::Craft(
+            data.recipes
+                .get(*name)
+                .ok_or(format!("unknown recipe \"{}\"", name))?,
+        ),
+        ["wait"] => SubAction::Wait,
+        ["jump", target] => SubAction::Jump(parse_index(target, "step")?),
+        ["jump_if", condition @ .., target] if !condition.is_empty() => {
+            SubAction::JumpIf(parse_condition(condition, data)?, parse_index(target, "step")?)
+        }
+        ["repeat", start, count] => {
+            SubAction::Repeat(parse_index(start, "step")?, parse_number(count, "repeat count")?)
+        }
+        ["call", name @ ..] if !name.is_empty() => {
+            let name = name.join(" ");
+            let callee = db
+                .ids()
+                .find(|id| db.get(*id).len() > 0 && db.get(*id).name == name)
+                .ok_or(format!("no recording named \"{}\"", name))?;
+            SubAction::Call(callee)
+        }
+        [verb, ..] if !ACTIONS.contains(verb) => return Err(format!("unknown action \"{}\"", verb)),
+        _ => return Err(bad_arguments(words)),
+    };
+    Ok(Action { direction, action })
+}
+
+fn parse_fallback(words: &[&str], db: &RecordingDb, data: &'static Data) -> LineResult<FallbackPolicy> {
+    Ok(match words {
+        ["paradox"] => FallbackPolicy::Paradox,
+        ["retry"] => FallbackPolicy::Retry,
+        ["wait"] => FallbackPolicy::Wait,
+        ["skip"] => FallbackPolicy::Skip,
+        ["despawn"] => FallbackPolicy::Despawn,
+        ["alternate", step @ ..] => {
+            let action = parse_step(step, db, data)?;
+            if matches!(
+                action.action,
+                SubAction::Jump(_) | SubAction::JumpIf(_, _) | SubAction::Repeat(_, _) | SubAction::Call(_)
+            ) {
+                return Err("alternate actions can't be control steps".to_string());
+            }
+            FallbackPolicy::Alternate(action)
+        }
+        [] => return Err("missing fallback".to_string()),
+        [kind, ..] => return Err(format!("unknown fallback \"{}\"", kind)),
+    })
+}
+
+// Cloners would lose their recording on the way through text, so they are refused.
+fn lookup_inventory_item(name: &str, data: &'static Data) -> LineResult<&'static ObjectDescriptor> {
+    let item = lookup_item(name, data)?;
+    if item.on_use.as_deref() == Some("action_use_cloner") {
+        return Err(format!("\"{}\" can't be imported, as its recording isn't saved", name));
+    }
+    Ok(item)
+}
+
+fn parse_inventory(text: &str, data: &'static Data) -> LineResult<BasicInventory> {
+    let mut items = [None; 5];
+    let slots: Vec<&str> = text.split(',').map(str::trim).collect();
+    if slots.len() > items.len() {
+        return Err(format!("inventories hold at most {} items", items.len()));
+    }
+    for (slot, entry) in items.iter_mut().zip(slots) {
+        let words: Vec<&str> = entry.split_whitespace().collect();
+        match words.as_slice() {
+            ["-"] => {}
+            [name] => *slot = Some(Item::new(lookup_inventory_item(name, data)?, 1)),
+            [name, count] => {
+                let count = count
+                    .strip_prefix('x')
+                    .and_then(|c| c.parse().ok())
+                    .filter(|c| *c > 0)
+                    .ok_or(format!("expected a count like x2, found \"{}\"", count))?;
+                *slot = Some(Item::new(lookup_inventory_item(name, data)?, count));
+            }
+            _ => return Err(format!("expected an item name, found \"{}\"", entry)),
+        }
+    }
+    Ok(BasicInventory::from_items(items))
+}
+
+// Control steps must point inside the recording. The last step number plus one finishes it.
+fn check_targets(idx: usize, action: &SubAction, len: usize) -> LineResult<()> {
+    match *action {
+        SubAction::Jump(target) | SubAction::JumpIf(_, target) if target > len => {
+            Err(format!("step {} is past the end of the recording", target + 1))
+        }
+        SubAction::Repeat(start, _) if start >= idx => {
+            Err("repeat must come after the steps it repeats".to_string())
+        }
+        SubAction::Repeat(_, 0) => Err("repeat count must be at least 1".to_string()),
+        _ => Ok(()),
+    }
+}
+
+pub fn from_text(text: &str, db: &RecordingDb, data: &'static Data) -> Result<Recording, ParseError> {
+    let mut recording = Recording::blank();
+    // Line of each step, for errors found once all steps are known.
+    let mut step_lines = Vec::new();
+
+    for (number, line) in text.lines().enumerate().map(|(n, l)| (n + 1, l.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: String| ParseError { line: Some(number), message };
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        match keyword {
+            "name" => recording.name = rest.to_string(),
+            "note" => recording.note = rest.to_string(),
+            "loop" if words.is_empty() => recording.should_loop = true,
+            "plays" => {
+                recording.repeat_count = parse_number(rest, "number of plays").map_err(error)?;
+                if recording.repeat_count == 0 {
+                    return Err(error("recordings play at least once".to_string()));
+                }
+            }
+            "recorder" => {
+                let item = lookup_item(rest, data).map_err(error)?;
+                if item.recorder.is_none() {
+                    return Err(error(format!("\"{}\" is not a recorder", item.name)));
+                }
+                recording.recorder = Some(item);
+            }
+            "inventory" => recording.inventory = parse_inventory(rest, data).map_err(error)?,
+            "fallback" => recording.fallback = parse_fallback(&words, db, data).map_err(error)?,
+            _ => {
+                let (step, fallback) = match line.split_once('|') {
+                    Some((step, fallback)) => (step, Some(fallback)),
+                    None => (line, None),
+                };
+                let step_words: Vec<&str> = step.split_whitespace().collect();
+                let action = parse_step(&step_words, db, data).map_err(error)?;
+                if let Some(fallback) = fallback {
+                    let fallback_words: Vec<&str> = fallback.split_whitespace().collect();
+                    let policy = parse_fallback(&fallback_words, db, data).map_err(error)?;
+                    recording.step_fallbacks.insert(recording.len(), policy);
+                }
+                recording.append(action);
+                step_lines.push(number);
+            }
+        }
+    }
+
+    for (idx, (step, line)) in recording.command_list.iter().zip(step_lines).enumerate() {
+        check_targets(idx, &step.action, recording.len())
+            .map_err(|message| ParseError { line: Some(line), message })?;
+    }
+    // Without a recorder there would be no limits to check the recording against.
+    if recording.recorder.is_none() {
+        return Err(ParseError {
+            line: None,
+            message: "missing recorder line".to_string(),
+        });
+    }
+    Ok(recording)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::static_data::Data;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data = Data::get_test_config();
+        let mut db = RecordingDb::new();
+        let callee = db.register_recording(Recording {
+            name: "fetch ore".to_string(),
+            command_list: vec![Action {
+                direction: Direction::Relative(RelativeDirection::F),
+                action: SubAction::Take,
+            }],
+            ..Recording::blank()
+        });
+
+        let text = "\
+name patrol
+# Comments and blank lines are skipped.
+recorder temporal_recorder
+
+loop
+inventory echo_crystal x2, -, raw_crystal
+fallback retry
+move
+R take | alternate L move
+N craft recorder
+use 2
+jump_if carrying raw_crystal 1
+B repeat 1 3
+call fetch ore | skip
+";
+        let recording = from_text(text, &db, data).unwrap();
+        assert_eq!(recording.name, "patrol");
+        assert!(recording.should_loop);
+        assert_eq!(recording.fallback, FallbackPolicy::Retry);
+        assert_eq!(recording.inventory.get_items()[0].unwrap().quantity, 2);
+        assert!(recording.inventory.get_items()[1].is_none());
+        assert_eq!(recording.at(3).action, SubAction::Use(1));
+        assert_eq!(recording.at(5).action, SubAction::Repeat(0, 3));
+        assert_eq!(recording.at(6).action, SubAction::Call(callee));
+        assert_eq!(recording.fallback_at(6), FallbackPolicy::Skip);
+
+        let again = from_text(&to_text(&recording, &db, data), &db, data).unwrap();
+        assert_eq!(again.command_list, recording.command_list);
+        assert_eq!(again.step_fallbacks, recording.step_fallbacks);
+        assert_eq!(again.inventory, recording.inventory);
+        assert_eq!(to_text(&again, &db, data), to_text(&recording, &db, data));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let data = Data::get_test_config();
+        let db = RecordingDb::new();
+        let error = |text: &str| from_text(text, &db, data).err().unwrap();
+
+        assert_eq!(error("move\n\nfly").line, Some(3));
+        assert_eq!(error("move\nuse 0").message, "slot numbers start at 1");
+        assert_eq!(error("craft nothing").message, "unknown recipe \"nothing\"");
+        // Targets are checked once every step is known.
+        assert_eq!(error("jump 5\nmove\nmove").line, Some(1));
+        assert_eq!(error("move\nrepeat 2 2").line, Some(2));
+        // A missing recorder isn't the fault of any one line, even when the others are all valid.
+        let missing = error("name patrol\nmove");
+        assert_eq!((missing.line, missing.message.as_str()), (None, "missing recorder line"));
+        assert_eq!(missing.to_string(), "missing recorder line");
+        assert_eq!(error("recorder recorder\ninventory basic_cloner").line, Some(2));
+    }
+}
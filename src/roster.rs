@@ -0,0 +1,179 @@
+//! Every npc with a place in the event queue, and controls for the player's clones.
+
+use crate::datatypes::Coordinate;
+use crate::engine::tracking_worldlayer::TrackableId;
+use crate::engine::update::{Delta, UpdatableContainer};
+use crate::error::{
+    Result,
+    Status::{ActionFail, Error},
+};
+use crate::eventlog::LogEvent;
+use crate::eventqueue::{ActorEvent, StepResult};
+use crate::game_state::game::{Game, GameUpdate};
+use crate::inventory::BasicInventory;
+use crate::recording::db::RecordingId;
+use crate::{devtools, static_data::ObjectDescriptor};
+
+pub struct RosterEntry {
+    pub actor: TrackableId,
+    pub descriptor: &'static ObjectDescriptor,
+    pub recording: RecordingId,
+    pub step: usize,
+    // Turn of its next event. Paused npcs keep the turn they were due on.
+    pub turn: i64,
+    pub paused: bool,
+    pub location: Coordinate,
+    pub inventory: BasicInventory,
+    // Paradox the npc can stand before it dies. None if it can't be killed by paradox.
+    pub paradox_left: Option<f64>,
+    pub last_result: Option<StepResult>,
+}
+
+impl RosterEntry {
+    // Only the player's clones can be paused or terminated.
+    pub fn controllable(&self) -> bool {
+        self.descriptor.name == "clone"
+    }
+}
+
+fn entry(game: &Game, turn: i64, evt: &ActorEvent, paused: bool) -> Result<RosterEntry> {
+    let location = *game.world.actors.get_location(&evt.actor)?;
+    let cell = game.world.get_cell(&location)?;
+    let actor = cell.actor.ok_or(Error("No actor at expected coordinates"))?;
+    Ok(RosterEntry {
+        actor: evt.actor,
+        descriptor: actor.descriptor,
+        recording: evt.recording,
+        step: evt.recording_idx,
+        turn,
+        paused,
+        location,
+        inventory: actor.inventory,
+        paradox_left: actor.descriptor.hp.map(|hp| hp as f64 - cell.paradox.0),
+        last_result: evt.last_result.clone(),
+    })
+}
+
+// One entry per npc, in the order they next act. Paused npcs come last.
+// Events whose actor can't be found in the world are left out, so one stale event doesn't hide the rest.
+pub fn roster(game: &Game) -> Vec<RosterEntry> {
+    let queue = &game.event_queue;
+    let events = queue
+        .queued()
+        .map(|(turn, evt)| (turn, evt, false))
+        .chain(queue.paused.iter().map(|(turn, evt)| (*turn, evt, true)));
+    let mut entries: Vec<RosterEntry> = Vec::new();
+    for (turn, evt, paused) in events {
+        if entries.iter().any(|e| e.actor == evt.actor) {
+            continue;
+        }
+        if let Ok(entry) = entry(game, turn, evt, paused) {
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+fn check_clone(game: &Game, actor: TrackableId) -> Result<()> {
+    let location = game.world.actors.get_location(&actor)?;
+    match game.world.actors.get(location)? {
+        Some(a) if a.descriptor.name == "clone" => Ok(()),
+        Some(_) => Err(ActionFail("only clones can be controlled")),
+        None => Err(Error("No actor at expected coordinates")),
+    }
+}
+
+pub fn pause(game: &mut Game, actor: TrackableId) -> Result<()> {
+    check_clone(game, actor)?;
+    if game.event_queue.is_paused(actor) {
+        return Err(ActionFail("clone is already paused"));
+    }
+    if !game.event_queue.pause(actor) {
+        return Err(ActionFail("clone has nothing left to do"));
+    }
+    game.history.clear();
+    Ok(())
+}
+
+pub fn resume(game: &mut Game, actor: TrackableId) -> Result<()> {
+    check_clone(game, actor)?;
+    if !game.event_queue.resume(actor) {
+        return Err(ActionFail("clone isn't paused"));
+    }
+    game.history.clear();
+    Ok(())
+}
+
+// Despawns the clone, along with anything it was carrying.
+pub fn terminate(game: &mut Game, actor: TrackableId) -> Result<()> {
+    check_clone(game, actor)?;
+    let location = *game.world.actors.get_location(&actor)?;
+    let mut update = GameUpdate::new();
+    devtools::despawn_actor(actor, game, &mut update)?;
+    update.apply(game)?;
+    game.log_event(Some(actor), location, LogEvent::Despawned);
+    game.history.clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::{Action, SubAction};
+    use crate::direction::{AbsoluteDirection, Direction::Absolute};
+    use crate::inventory::Item;
+    use crate::recording::Recording;
+    use crate::static_data::Data;
+
+    use super::*;
+
+    #[test]
+    fn clone_controls() {
+        let data = Data::get_test_config();
+        let mut game = Game::new(Coordinate { x: 1, y: 3 }, data);
+        game.spawn(&Coordinate { x: 0, y: 0 }).unwrap();
+        let north = |action| Action {
+            direction: Absolute(AbsoluteDirection::N),
+            action,
+        };
+        let id = game.recordings.recordings.register_recording(Recording {
+            command_list: vec![north(SubAction::Wait), north(SubAction::Take)],
+            should_loop: true,
+            ..Recording::blank()
+        });
+        let cloner = Item::new_cloner(data.items.get("basic_cloner").unwrap(), id);
+        devtools::grant_item(cloner, Coordinate { x: 0, y: 0 }, &game).unwrap().apply(&mut game).unwrap();
+        game.player_action_and_turn(north(SubAction::Use(0))).unwrap();
+
+        let entries = roster(&game);
+        assert_eq!(entries.len(), 1);
+        let clone = entries[0].actor;
+        assert_eq!(entries[0].location, Coordinate { x: 0, y: 1 });
+        assert_eq!((entries[0].step, entries[0].turn), (1, 1));
+        assert_eq!(entries[0].last_result, Some(StepResult::Done));
+        assert!(entries[0].controllable());
+
+        // An event for an actor that's gone doesn't stop the rest being listed.
+        let mut stale = game.scratch_copy();
+        stale.event_queue.this_turn.push_back(ActorEvent::new(TrackableId(99), id));
+        assert_eq!(roster(&stale).len(), 1);
+
+        // The clone stays put while paused, and picks up where it left off.
+        pause(&mut game, clone).unwrap();
+        assert_eq!(pause(&mut game, clone), Err(ActionFail("clone is already paused")));
+        game.player_action_and_turn(north(SubAction::Wait)).unwrap();
+        let entries = roster(&game);
+        assert!(entries[0].paused);
+        assert_eq!(entries[0].step, 1);
+        resume(&mut game, clone).unwrap();
+        game.player_action_and_turn(north(SubAction::Wait)).unwrap();
+        let entries = roster(&game);
+        assert_eq!(entries[0].step, 0);
+        assert_eq!(entries[0].last_result, Some(StepResult::Failed("no item to take".to_string())));
+
+        terminate(&mut game, clone).unwrap();
+        assert!(roster(&game).is_empty());
+        assert!(game.world.actors.get(&Coordinate { x: 0, y: 1 }).unwrap().is_none());
+        let player = game.actors.get_player().unwrap();
+        assert!(pause(&mut game, player).is_err());
+    }
+}
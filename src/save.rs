@@ -0,0 +1,635 @@
+//! Saving and loading of game state.
+//!
+//! Anything holding a reference into static data (item, actor and building definitions, recipes)
+//! is converted into a mirror struct that stores the definition by name, and re-bound against
+//! `Data` on load.
+
+use std::collections::HashMap;
+use std::fs;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::action::{Action, Condition, SubAction};
+use crate::actor::Actor;
+use crate::buildings::Building;
+use crate::datatypes::Coordinate;
+use crate::direction::{AbsoluteDirection, Direction};
+use crate::engine::tracking_worldlayer::TrackableId;
+use crate::engine::update::UpdatableContainer;
+use crate::error::{Result, Status::Error};
+use crate::eventqueue::{Deployment, EventQueue};
+use crate::game_state::game::{Game, PlayerRef};
+use crate::game_state::world::FloorTile;
+use crate::inventory::{BasicInventory, Item, SlotInventory};
+use crate::paradox::Paradox;
+use crate::recording::db::{RecordingDb, RecordingId};
+use crate::recording::interface::RecordingModule;
+use crate::recording::{FallbackPolicy, Recording};
+use crate::score::Score;
+use crate::static_data::Data;
+
+pub const DEFAULT_SAVE_PATH: &str = "savegame.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    dimensions: Coordinate,
+    player: Option<TrackableId>,
+    score: Score,
+    world: WorldSave,
+    recordings: RecordingModuleSave,
+    event_queue: EventQueue,
+}
+
+// Layers are stored densely, in the same row-major order as WorldLayer.
+#[derive(Serialize, Deserialize)]
+struct WorldSave {
+    actors: Vec<Option<ActorSave>>,
+    actor_index: Vec<(TrackableId, Coordinate)>,
+    actor_recycle: Vec<TrackableId>,
+    buildings: Vec<Option<BuildingSave>>,
+    items: Vec<[Option<ItemSave>; 1]>,
+    paradox: Vec<f64>,
+    floor: Vec<FloorTile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ItemSave {
+    definition: String,
+    quantity: u16,
+    recording: Option<RecordingId>,
+    deployment: Deployment,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ActorSave {
+    facing: AbsoluteDirection,
+    descriptor: String,
+    actor_id: TrackableId,
+    inventory: [Option<ItemSave>; 5],
+    paradox_level: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BuildingSave {
+    definition: String,
+    facing: AbsoluteDirection,
+    input: Vec<Option<ItemSave>>,
+    output: Vec<Option<ItemSave>>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SubActionSave {
+    Move,
+    Take,
+    Drop(usize),
+    Use(usize),
+    ActivateBuilding,
+    Insert(usize),
+    Extract,
+    Craft(String),
+    Wait,
+    Jump(usize),
+    JumpIf(ConditionSave, usize),
+    Repeat(usize, u32),
+    Call(RecordingId),
+}
+
+#[derive(Serialize, Deserialize)]
+enum ConditionSave {
+    FloorItemIs(String),
+    InventoryHas(String),
+    TileAheadOccupied,
+    ParadoxAbove(f64),
+}
+
+#[derive(Serialize, Deserialize)]
+struct ActionSave {
+    direction: Direction,
+    action: SubActionSave,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordingSave {
+    command_list: Vec<ActionSave>,
+    inventory: [Option<ItemSave>; 5],
+    should_loop: bool,
+    repeat_count: u32,
+    fallback: FallbackSave,
+    step_fallbacks: Vec<(usize, FallbackSave)>,
+    record_failures: bool,
+    name: String,
+    note: String,
+    recorder: Option<String>,
+    creator: Option<TrackableId>,
+    created_turn: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+enum FallbackSave {
+    Paradox,
+    Retry,
+    Wait,
+    Skip,
+    Alternate(ActionSave),
+    Despawn,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordingModuleSave {
+    recordings: Vec<RecordingSave>,
+    current_recording: Option<RecordingSave>,
+    temp_item: Option<ItemSave>,
+    last_player_facing: AbsoluteDirection,
+}
+
+fn lookup<T>(table: &'static HashMap<String, T>, name: &String) -> Result<&'static T> {
+    table
+        .get(name)
+        .ok_or(Error("save file refers to an unknown definition"))
+}
+
+impl ItemSave {
+    fn new(item: &Item) -> ItemSave {
+        ItemSave {
+            definition: item.definition.name.clone(),
+            quantity: item.quantity,
+            recording: item.recording,
+            deployment: item.deployment,
+        }
+    }
+
+    fn restore(&self, data: &'static Data) -> Result<Item> {
+        Ok(Item {
+            definition: lookup(&data.items, &self.definition)?,
+            quantity: self.quantity,
+            recording: self.recording,
+            deployment: self.deployment,
+        })
+    }
+}
+
+fn save_inventory(inventory: &BasicInventory) -> [Option<ItemSave>; 5] {
+    inventory.get_items().each_ref().map(|i| i.as_ref().map(ItemSave::new))
+}
+
+fn restore_inventory(saved: &[Option<ItemSave>; 5], data: &'static Data) -> Result<BasicInventory> {
+    let mut items = [None; 5];
+    for (slot, item) in items.iter_mut().zip(saved) {
+        if let Some(item) = item {
+            *slot = Some(item.restore(data)?);
+        }
+    }
+    Ok(BasicInventory::from_items(items))
+}
+
+fn save_slots(inventory: &SlotInventory) -> Vec<Option<ItemSave>> {
+    inventory.get_items().iter().map(|i| i.as_ref().map(ItemSave::new)).collect()
+}
+
+fn restore_slots(saved: &[Option<ItemSave>], data: &'static Data) -> Result<SlotInventory> {
+    let items = saved
+        .iter()
+        .map(|item| item.as_ref().map(|i| i.restore(data)).transpose())
+        .collect::<Result<Vec<_>>>()?;
+    Ok(SlotInventory::from_items(items))
+}
+
+impl ActorSave {
+    fn new(actor: &Actor) -> ActorSave {
+        ActorSave {
+            facing: actor.facing,
+            descriptor: actor.descriptor.name.clone(),
+            actor_id: actor.actor_id,
+            inventory: save_inventory(&actor.inventory),
+            paradox_level: actor.paradox_level,
+        }
+    }
+
+    fn restore(&self, data: &'static Data) -> Result<Actor> {
+        Ok(Actor {
+            facing: self.facing,
+            descriptor: lookup(&data.actors, &self.descriptor)?,
+            actor_id: self.actor_id,
+            inventory: restore_inventory(&self.inventory, data)?,
+            paradox_level: self.paradox_level,
+        })
+    }
+}
+
+impl BuildingSave {
+    fn new(building: &Building) -> BuildingSave {
+        BuildingSave {
+            definition: building.definition.name.clone(),
+            facing: building.facing,
+            input: save_slots(&building.input),
+            output: save_slots(&building.output),
+        }
+    }
+
+    fn restore(&self, data: &'static Data) -> Result<Building> {
+        Ok(Building {
+            definition: lookup(&data.buildings, &self.definition)?,
+            facing: self.facing,
+            input: restore_slots(&self.input, data)?,
+            output: restore_slots(&self.output, data)?,
+        })
+    }
+}
+
+impl ConditionSave {
+    fn new(condition: &Condition) -> ConditionSave {
+        match condition {
+            Condition::FloorItemIs(item) => ConditionSave::FloorItemIs(item.name.clone()),
+            Condition::InventoryHas(item) => ConditionSave::InventoryHas(item.name.clone()),
+            Condition::TileAheadOccupied => ConditionSave::TileAheadOccupied,
+            Condition::ParadoxAbove(threshold) => ConditionSave::ParadoxAbove(*threshold),
+        }
+    }
+
+    fn restore(&self, data: &'static Data) -> Result<Condition> {
+        Ok(match self {
+            ConditionSave::FloorItemIs(name) => Condition::FloorItemIs(lookup(&data.items, name)?),
+            ConditionSave::InventoryHas(name) => Condition::InventoryHas(lookup(&data.items, name)?),
+            ConditionSave::TileAheadOccupied => Condition::TileAheadOccupied,
+            ConditionSave::ParadoxAbove(threshold) => Condition::ParadoxAbove(*threshold),
+        })
+    }
+}
+
+impl ActionSave {
+    fn new(action: &Action, data: &Data) -> Result<ActionSave> {
+        let subaction = match action.action {
+            SubAction::Move => SubActionSave::Move,
+            SubAction::Take => SubActionSave::Take,
+            SubAction::Drop(idx) => SubActionSave::Drop(idx),
+            SubAction::Use(idx) => SubActionSave::Use(idx),
+            SubAction::ActivateBuilding => SubActionSave::ActivateBuilding,
+            SubAction::Insert(idx) => SubActionSave::Insert(idx),
+            SubAction::Extract => SubActionSave::Extract,
+            SubAction::Craft(recipe) => SubActionSave::Craft(
+                data.recipe_key(recipe)
+                    .cloned()
+                    .ok_or(Error("recipe missing from static data"))?,
+            ),
+            SubAction::Wait => SubActionSave::Wait,
+            SubAction::Jump(target) => SubActionSave::Jump(target),
+            SubAction::Repeat(start, count) => SubActionSave::Repeat(start, count),
+            SubAction::Call(callee) => SubActionSave::Call(callee),
+            SubAction::JumpIf(condition, target) => {
+                SubActionSave::JumpIf(ConditionSave::new(&condition), target)
+            }
+        };
+        Ok(ActionSave {
+            direction: action.direction,
+            action: subaction,
+        })
+    }
+
+    fn restore(&self, data: &'static Data) -> Result<Action> {
+        let subaction = match &self.action {
+            SubActionSave::Move => SubAction::Move,
+            SubActionSave::Take => SubAction::Take,
+            SubActionSave::Drop(idx) => SubAction::Drop(*idx),
+            SubActionSave::Use(idx) => SubAction::Use(*idx),
+            SubActionSave::ActivateBuilding => SubAction::ActivateBuilding,
+            SubActionSave::Insert(idx) => SubAction::Insert(*idx),
+            SubActionSave::Extract => SubAction::Extract,
+            SubActionSave::Craft(name) => SubAction::Craft(lookup(&data.recipes, name)?),
+            SubActionSave::Wait => SubAction::Wait,
+            SubActionSave::Jump(target) => SubAction::Jump(*target),
+            SubActionSave::Repeat(start, count) => SubAction::Repeat(*start, *count),
+            SubActionSave::Call(callee) => SubAction::Call(*callee),
+            SubActionSave::JumpIf(condition, target) => {
+                SubAction::JumpIf(condition.restore(data)?, *target)
+            }
+        };
+        Ok(Action {
+            direction: self.direction,
+            action: subaction,
+        })
+    }
+}
+
+impl FallbackSave {
+    fn new(policy: &FallbackPolicy, data: &Data) -> Result<FallbackSave> {
+        Ok(match policy {
+            FallbackPolicy::Paradox => FallbackSave::Paradox,
+            FallbackPolicy::Retry => FallbackSave::Retry,
+            FallbackPolicy::Wait => FallbackSave::Wait,
+            FallbackPolicy::Skip => FallbackSave::Skip,
+            FallbackPolicy::Alternate(action) => FallbackSave::Alternate(ActionSave::new(action, data)?),
+            FallbackPolicy::Despawn => FallbackSave::Despawn,
+        })
+    }
+
+    fn restore(&self, data: &'static Data) -> Result<FallbackPolicy> {
+        Ok(match self {
+            FallbackSave::Paradox => FallbackPolicy::Paradox,
+            FallbackSave::Retry => FallbackPolicy::Retry,
+            FallbackSave::Wait => FallbackPolicy::Wait,
+            FallbackSave::Skip => FallbackPolicy::Skip,
+            FallbackSave::Alternate(action) => FallbackPolicy::Alternate(action.restore(data)?),
+            FallbackSave::Despawn => FallbackPolicy::Despawn,
+        })
+    }
+}
+
+impl RecordingSave {
+    fn new(recording: &Recording, data: &Data) -> Result<RecordingSave> {
+        Ok(RecordingSave {
+            command_list: recording
+                .command_list
+                .iter()
+                .map(|a| ActionSave::new(a, data))
+                .collect::<Result<_>>()?,
+            inventory: save_inventory(&recording.inventory),
+            should_loop: recording.should_loop,
+            repeat_count: recording.repeat_count,
+            fallback: FallbackSave::new(&recording.fallback, data)?,
+            step_fallbacks: recording
+                .step_fallbacks
+                .iter()
+                .map(|(idx, policy)| Ok((*idx, FallbackSave::new(policy, data)?)))
+                .collect::<Result<_>>()?,
+            name: recording.name.clone(),
+            note: recording.note.clone(),
+            recorder: recording.recorder.map(|def| def.name.clone()),
+            creator: recording.creator,
+            record_failures: recording.record_failures,
+            created_turn: recording.created_turn,
+        })
+    }
+
+    fn restore(&self, data: &'static Data) -> Result<Recording> {
+        Ok(Recording {
+            command_list: self
+                .command_list
+                .iter()
+                .map(|a| a.restore(data))
+                .collect::<Result<_>>()?,
+            inventory: restore_inventory(&self.inventory, data)?,
+            should_loop: self.should_loop,
+            repeat_count: self.repeat_count,
+            fallback: self.fallback.restore(data)?,
+            step_fallbacks: self
+                .step_fallbacks
+                .iter()
+                .map(|(idx, policy)| Ok((*idx, policy.restore(data)?)))
+                .collect::<Result<_>>()?,
+            name: self.name.clone(),
+            note: self.note.clone(),
+            recorder: self
+                .recorder
+                .as_ref()
+                .map(|name| lookup(&data.items, name))
+                .transpose()?,
+            creator: self.creator,
+            record_failures: self.record_failures,
+            created_turn: self.created_turn,
+        })
+    }
+}
+
+impl RecordingModuleSave {
+    fn new(module: &RecordingModule, data: &Data) -> Result<RecordingModuleSave> {
+        Ok(RecordingModuleSave {
+            recordings: module
+                .recordings
+                .get_all()
+                .iter()
+                .map(|r| RecordingSave::new(r, data))
+                .collect::<Result<_>>()?,
+            current_recording: module
+                .current_recording
+                .as_ref()
+                .map(|r| RecordingSave::new(r, data))
+                .transpose()?,
+            temp_item: module.temp_item.as_ref().map(ItemSave::new),
+            last_player_facing: module.last_player_facing,
+        })
+    }
+
+    fn restore(&self, data: &'static Data) -> Result<RecordingModule> {
+        let mut module = RecordingModule::new();
+        module.recordings = RecordingDb::from_recordings(
+            self.recordings
+                .iter()
+                .map(|r| r.restore(data))
+                .collect::<Result<_>>()?,
+        );
+        module.current_recording = self
+            .current_recording
+            .as_ref()
+            .map(|r| r.restore(data))
+            .transpose()?;
+        module.temp_item = self.temp_item.as_ref().map(|i| i.restore(data)).transpose()?;
+        module.last_player_facing = self.last_player_facing;
+        Ok(module)
+    }
+}
+
+fn save_layer<L, S>(
+    layer: &L,
+    dimensions: Coordinate,
+    f: impl Fn(&L::DataType) -> S,
+) -> Result<Vec<S>>
+where
+    L: UpdatableContainer<CoordinateType = Coordinate>,
+{
+    let mut ret = Vec::new();
+    for y in 0..dimensions.y {
+        for x in 0..dimensions.x {
+            ret.push(f(layer.get(&Coordinate { x, y })?));
+        }
+    }
+    Ok(ret)
+}
+
+fn load_layer<L, S>(
+    layer: &mut L,
+    dimensions: Coordinate,
+    values: &[S],
+    f: impl Fn(&S) -> Result<L::DataType>,
+) -> Result<()>
+where
+    L: UpdatableContainer<CoordinateType = Coordinate>,
+{
+    if values.len() != (dimensions.x * dimensions.y) as usize {
+        return Err(Error("save file layer does not match world dimensions"));
+    }
+    for (i, value) in values.iter().enumerate() {
+        let location = Coordinate {
+            x: i as i32 % dimensions.x,
+            y: i as i32 / dimensions.x,
+        };
+        layer.mut_set(&location, &f(value)?)?;
+    }
+    Ok(())
+}
+
+impl SaveFile {
+    pub fn new(game: &Game) -> Result<SaveFile> {
+        let world = &game.world;
+        let dimensions = world.dimensions();
+        let (mut actor_index, mut actor_recycle) = world.actors.get_index_entries();
+        // Sorted so that saving the same game twice yields the same file.
+        actor_index.sort_by_key(|(id, _)| id.0);
+        actor_recycle.sort_by_key(|id| id.0);
+
+        Ok(SaveFile {
+            dimensions,
+            player: game.actors.player.as_ref().map(|p| p.actor_id),
+            score: Score {
+                score: game.score.score,
+                turn: game.score.turn,
+            },
+            world: WorldSave {
+                actors: save_layer(&world.actors, dimensions, |a| a.as_ref().map(ActorSave::new))?,
+                actor_index,
+                actor_recycle,
+                buildings: save_layer(&world.buildings, dimensions, |b| {
+                    b.as_ref().map(BuildingSave::new)
+                })?,
+                items: save_layer(&world.items, dimensions, |i| {
+                    i.each_ref().map(|i| i.as_ref().map(ItemSave::new))
+                })?,
+                paradox: save_layer(&world.paradox, dimensions, |p| p.0)?,
+                floor: save_layer(&world.floor, dimensions, |f| *f)?,
+            },
+            recordings: RecordingModuleSave::new(&game.recordings, game.data)?,
+            event_queue: game.event_queue.clone(),
+        })
+    }
+
+    pub fn restore(&self, data: &'static Data) -> Result<Game> {
+        let mut game = Game::new(self.dimensions, data);
+        let dimensions = self.dimensions;
+        let world = &self.world;
+
+        load_layer(&mut game.world.actors, dimensions, &world.actors, |a| {
+            a.as_ref().map(|a| a.restore(data)).transpose()
+        })?;
+        game.world
+            .actors
+            .mut_restore_index(&world.actor_index, &world.actor_recycle);
+        load_layer(&mut game.world.buildings, dimensions, &world.buildings, |b| {
+            b.as_ref().map(|b| b.restore(data)).transpose()
+        })?;
+        load_layer(&mut game.world.items, dimensions, &world.items, |[i]| {
+            Ok([i.as_ref().map(|i| i.restore(data)).transpose()?])
+        })?;
+        load_layer(&mut game.world.paradox, dimensions, &world.paradox, |p| {
+            Ok(Paradox(*p))
+        })?;
+        load_layer(&mut game.world.floor, dimensions, &world.floor, |f| Ok(*f))?;
+
+        game.actors.player = self.player.map(|actor_id| PlayerRef { actor_id });
+        game.recordings = self.recordings.restore(data)?;
+        game.event_queue = self.event_queue.clone();
+        game.score = Score {
+            score: self.score.score,
+            turn: self.score.turn,
+        };
+        Ok(game)
+    }
+}
+
+pub fn save_game(game: &Game) -> Result<String> {
+    let save = SaveFile::new(game)?;
+    serde_json::to_string(&save).map_err(|e| {
+        log::log!(log::Level::Error, "{}", e);
+        Error("unable to serialize game")
+    })
+}
+
+pub fn load_game(contents: &str, data: &'static Data) -> Result<Game> {
+    let save: SaveFile = serde_json::from_str(contents).map_err(|e| {
+        log::log!(log::Level::Error, "{}", e);
+        Error("unable to parse save file")
+    })?;
+    save.restore(data)
+}
+
+// Parse a JSON list of actions, in the same format recordings use in save files.
+pub fn load_actions(contents: &str, data: &'static Data) -> Result<Vec<Action>> {
+    let actions: Vec<ActionSave> = serde_json::from_str(contents).map_err(|e| {
+        log::log!(log::Level::Error, "{}", e);
+        Error("unable to parse action list")
+    })?;
+    actions.iter().map(|a| a.restore(data)).collect()
+}
+
+pub fn save_to_file(game: &Game, path: &str) -> Result<()> {
+    let contents = save_game(game)?;
+    fs::write(path, contents).map_err(|e| {
+        log::log!(log::Level::Error, "{}", e);
+        Error("unable to write save file")
+    })
+}
+
+pub fn load_from_file(path: &str, data: &'static Data) -> Result<Game> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        log::log!(log::Level::Error, "{}", e);
+        Error("unable to read save file")
+    })?;
+    load_game(&contents, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::{Action, SubAction};
+    use crate::direction::{AbsoluteDirection, Direction};
+    use crate::worldgen;
+
+    use super::*;
+
+    #[test]
+    fn action_list() {
+        let data = Data::get_test_config();
+        let actions = load_actions(
+            r#"[{"direction": {"Relative": "F"}, "action": "Take"},
+                {"direction": {"Absolute": "N"}, "action": {"Craft": "echo_crystal"}}]"#,
+            data,
+        )
+        .unwrap();
+        assert_eq!(actions[0].action, SubAction::Take);
+        assert_eq!(
+            actions[1].action,
+            SubAction::Craft(data.recipes.get("echo_crystal").unwrap())
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = Data::get_test_config();
+        let game = worldgen::start_game(data);
+        let mut game = game.borrow_mut();
+
+        let recipe = data.recipes.get("echo_crystal").unwrap();
+        game.recordings.current_recording = Some(Recording::blank());
+        game.recordings.append(Action {
+            direction: Direction::Absolute(AbsoluteDirection::E),
+            action: SubAction::Craft(recipe),
+        });
+        game.player_action_and_turn(Action {
+            direction: Direction::Absolute(AbsoluteDirection::E),
+            action: SubAction::Move,
+        })
+        .unwrap();
+
+        let saved = save_game(&game).unwrap();
+        let loaded = load_game(&saved, data).unwrap();
+
+        assert_eq!(saved, save_game(&loaded).unwrap());
+        assert_eq!(game.get_player_coords(), loaded.get_player_coords());
+        assert_eq!(game.score.turn, loaded.score.turn);
+        assert_eq!(
+            game.event_queue.this_turn.len() + game.event_queue.next_turn.len(),
+            loaded.event_queue.this_turn.len() + loaded.event_queue.next_turn.len()
+        );
+        assert_eq!(
+            loaded.recordings.current_recording.unwrap().command_list[0].action,
+            SubAction::Craft(recipe)
+        );
+    }
+}
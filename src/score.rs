@@ -1,5 +1,7 @@
-use crate::engine::update::{Delta, Updatable};
+use crate::engine::update::{Delta, MergeableDelta, Updatable};
+use serde_derive::{Deserialize, Serialize};
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Score{
     pub score: i64,
     pub turn: i64
@@ -22,3 +24,14 @@ fn apply(&self, target: &mut Self::Target) -> crate::error::Result<()> {
     Ok(())
 }
 }
+
+// Score changes always add up, so they never conflict.
+impl MergeableDelta for ScoreDelta {
+    fn check_conflicts(&self, _other: &Self) -> crate::error::Result<()> {
+        Ok(())
+    }
+
+    fn absorb(&mut self, other: Self) {
+        self.score += other.score;
+    }
+}
@@ -1,7 +1,8 @@
 //! Functions for loading external game data.
 
 use crate::action::{get_use_fn_table, ItemUseFn};
-use crate::buildings::{get_building_fn_table, BuildingUseFn};
+use crate::buildings::{get_building_fn_table, get_building_tick_fn_table, BuildingTickFn, BuildingUseFn};
+use crate::direction::AbsoluteDirection;
 use serde_derive::Deserialize;
 use std::collections::HashMap;
 use std::fs;
@@ -30,10 +31,40 @@ pub struct ObjectDescriptor {
     #[serde(skip_deserializing)]
     pub on_interact_fn: Option<BuildingUseFn>,
 
+    /// The building's function, run once every turn.
+    pub on_tick: Option<String>,
+    #[serde(skip_deserializing)]
+    pub on_tick_fn: Option<BuildingTickFn>,
+
+    /// Number of slots items can be inserted into, when used as a building.
+    pub input_slots: Option<usize>,
+
+    /// Number of slots items can be extracted from, when used as a building.
+    /// Buildings without output slots give up the contents of their input instead (e.g. chests).
+    pub output_slots: Option<usize>,
+
+    /// Turns taken to construct the object as a building. Defaults to one.
+    pub build_turns: Option<u32>,
+
+    /// What the item can record, when used as a recorder.
+    pub recorder: Option<RecorderDefinition>,
+
     pub text: ObjectText,
     pub appearance: AppearanceDefiniton
 }
 
+/// Limits and abilities of a recorder item.
+#[derive(Debug, Deserialize)]
+pub struct RecorderDefinition {
+    /// Most steps a recording can hold. No limit if absent.
+    pub max_steps: Option<usize>,
+    pub allow_loop: bool,
+    /// Conditional jumps.
+    pub allow_conditionals: bool,
+    /// Keep actions that failed while recording.
+    pub record_failures: bool,
+}
+
 /// Player-visible text related to an object. 
 /// Swappable for localization
 #[derive(Debug, Deserialize)]
@@ -56,9 +87,25 @@ pub struct AppearanceDefiniton {
 
     pub texture: Option<String>,
 
+    // Draw an arrow over the object showing which way it faces.
+    pub arrow: Option<bool>,
+
     pub color: String,
 }
 
+impl AppearanceDefiniton {
+    // The directional glyph if one is defined, otherwise the default glyph.
+    pub fn glyph_facing(&self, facing: AbsoluteDirection) -> &str {
+        let directional = match facing {
+            AbsoluteDirection::N => &self.glyph_n,
+            AbsoluteDirection::E => &self.glyph_e,
+            AbsoluteDirection::S => &self.glyph_s,
+            AbsoluteDirection::W => &self.glyph_w,
+        };
+        directional.as_ref().unwrap_or(&self.glyph)
+    }
+}
+
 impl PartialEq for ObjectDescriptor {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name
@@ -73,6 +120,10 @@ pub struct RecipeDefiniton {
     pub product: String,
     pub product_count: i64,
     pub name: String,
+    // Building the crafter must be standing on or facing. None means the recipe can be crafted anywhere.
+    pub station: Option<String>,
+    // Turns taken to craft. Defaults to one.
+    pub turns: Option<u32>,
 }
 
 #[derive(Default, Deserialize)]
@@ -95,6 +146,14 @@ pub fn asset_path(input: &String) -> String {
 }
 
 impl Data {
+    // Recipes don't carry their table key, so find it by value.
+    pub fn recipe_key(&self, recipe: &RecipeDefiniton) -> Option<&String> {
+        self.recipes
+            .iter()
+            .find(|(_, r)| *r == recipe)
+            .map(|(k, _)| k)
+    }
+
     pub fn get_config() -> &'static Data {
         let mut data = Data::read();
         data.bind_functions();
@@ -165,6 +224,18 @@ impl Data {
                 buildingdef.on_interact_fn = Some(*function);
             }
         }
+
+        // Unlike on_interact, there is no default tick function: most buildings are idle.
+        let tick_functions = get_building_tick_fn_table();
+        for (_, buildingdef) in self.buildings.iter_mut() {
+            if let Some(function) = buildingdef
+                .on_tick
+                .as_ref()
+                .and_then(|name| tick_functions.get(name))
+            {
+                buildingdef.on_tick_fn = Some(*function);
+            }
+        }
     }
 }
 
@@ -181,4 +252,33 @@ mod tests {
             "action_use_cloner"
         );
     }
+
+    #[test]
+    fn bind_tick_functions() {
+        let data = Data::get_test_config();
+        assert!(data.buildings["auto_miner"].on_tick_fn.is_some());
+        assert!(data.buildings["crystal_deposit"].on_tick_fn.is_none());
+    }
+
+    // Saves store definitions by name and look them up as table keys when loading.
+    #[test]
+    fn names_match_keys() {
+        let data = Data::get_test_config();
+        for table in [&data.actors, &data.buildings, &data.items] {
+            for (key, descriptor) in table {
+                assert_eq!(key, &descriptor.name, "definition {} has name {}", key, descriptor.name);
+            }
+        }
+    }
+
+    #[test]
+    fn directional_glyphs() {
+        let data = Data::get_test_config();
+        let conveyor = &data.buildings["conveyor"].appearance;
+        assert_eq!(conveyor.glyph_facing(AbsoluteDirection::N), "^");
+        assert_eq!(conveyor.glyph_facing(AbsoluteDirection::W), "<");
+        // Without directional glyphs, every facing uses the default one.
+        let chest = &data.buildings["chest"].appearance;
+        assert_eq!(chest.glyph_facing(AbsoluteDirection::E), chest.glyph);
+    }
 }
@@ -7,10 +7,15 @@ use crate::{
     buildings::Building, datatypes::Coordinate, game_state::game::Game, static_data::Data,
 };
 use crate::devtools;
+use crate::direction::AbsoluteDirection;
 
 use crate::engine::update::UpdatableContainer;
 
 pub fn start_game(data: &'static Data) -> Rc<RefCell<Game>> {
+    Rc::new(RefCell::new(generate_game(data)))
+}
+
+pub fn generate_game(data: &'static Data) -> Game {
     let mut game = Game::new(Coordinate { x: 60, y: 60 }, data);
 
     game.spawn(&Coordinate { x: 1, y: 1 }).unwrap();
@@ -24,7 +29,7 @@ pub fn start_game(data: &'static Data) -> Rc<RefCell<Game>> {
             .buildings
             .mut_set(
                 &Coordinate { x: i.0, y: i.1 },
-                &Some(Building { definition: ore }),
+                &Some(Building::new(ore, AbsoluteDirection::S)),
             )
             .unwrap();
     }
@@ -67,12 +72,8 @@ pub fn start_game(data: &'static Data) -> Rc<RefCell<Game>> {
             .actors
             .mut_set(&Coordinate { x: i.0, y: i.1 }, &Some(foeactor))
             .unwrap();
-        game.event_queue.next_turn.push_back(ActorEvent {
-            actor: id,
-            recording: recording_id,
-            recording_idx: 0,
-        });
+        game.event_queue.schedule_in(1, ActorEvent::new(id, recording_id));
     }
 
-    Rc::new(RefCell::new(game))
+    game
 }